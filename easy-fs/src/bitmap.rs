@@ -0,0 +1,100 @@
+use super::{get_block_cache, BlockDevice, BLOCK_SZ};
+use alloc::sync::Arc;
+/// A bitmap block
+type BitmapBlock = [u64; 64];
+/// Number of bits in a block
+const BLOCK_BITS: usize = BLOCK_SZ * 8;
+/// A bitmap
+pub struct Bitmap {
+    start_block_id: usize,
+    blocks: usize,
+}
+
+/// Decompose bits into (block_pos, bits64_pos, inner_pos)
+fn decomposition(mut bit: usize) -> (usize, usize, usize) {
+    let block_pos = bit / BLOCK_BITS;
+    bit %= BLOCK_BITS;
+    (block_pos, bit / 64, bit % 64)
+}
+
+impl Bitmap {
+    /// A new bitmap from start block id and number of blocks
+    pub fn new(start_block_id: usize, blocks: usize) -> Self {
+        Self {
+            start_block_id,
+            blocks,
+        }
+    }
+    /// Allocate a new block from a block device
+    pub fn alloc(&self, block_device: &Arc<dyn BlockDevice>) -> Option<usize> {
+        for block_id in 0..self.blocks {
+            let pos = get_block_cache(block_id + self.start_block_id, Arc::clone(block_device))
+                .lock()
+                .modify(0, |bitmap_block: &mut BitmapBlock| {
+                    if let Some((bits64_pos, inner_pos)) = bitmap_block
+                        .iter()
+                        .enumerate()
+                        .find(|(_, bits64)| **bits64 != u64::MAX)
+                        .map(|(bits64_pos, bits64)| (bits64_pos, bits64.trailing_ones() as usize))
+                    {
+                        // modify cache
+                        bitmap_block[bits64_pos] |= 1u64 << inner_pos;
+                        Some(block_id * BLOCK_BITS + bits64_pos * 64 + inner_pos)
+                    } else {
+                        None
+                    }
+                });
+            if pos.is_some() {
+                return pos;
+            }
+        }
+        None
+    }
+    /// Deallocate a block
+    pub fn dealloc(&self, block_device: &Arc<dyn BlockDevice>, bit: usize) {
+        let (block_pos, bits64_pos, inner_pos) = decomposition(bit);
+        get_block_cache(block_pos + self.start_block_id, Arc::clone(block_device))
+            .lock()
+            .modify(0, |bitmap_block: &mut BitmapBlock| {
+                assert!(bitmap_block[bits64_pos] & (1u64 << inner_pos) > 0);
+                bitmap_block[bits64_pos] -= 1u64 << inner_pos;
+            });
+    }
+    /// Get the max number of allocatable blocks
+    pub fn maximum(&self) -> usize {
+        self.blocks * BLOCK_BITS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bitmap, BLOCK_BITS};
+    use crate::block_dev::test_device::create_device;
+
+    #[test]
+    fn bits_are_handed_out_in_order_and_reused() {
+        let (_guard, device) = create_device(2);
+        let bitmap = Bitmap::new(0, 2);
+        assert_eq!(bitmap.maximum(), 2 * BLOCK_BITS);
+        for bit in 0..100 {
+            assert_eq!(bitmap.alloc(&device), Some(bit));
+        }
+        bitmap.dealloc(&device, 42);
+        bitmap.dealloc(&device, 7);
+        assert_eq!(bitmap.alloc(&device), Some(7));
+        assert_eq!(bitmap.alloc(&device), Some(42));
+        assert_eq!(bitmap.alloc(&device), Some(100));
+    }
+
+    #[test]
+    fn full_bitmap_spills_into_next_block_then_fails() {
+        let (_guard, device) = create_device(2);
+        let bitmap = Bitmap::new(0, 2);
+        for bit in 0..bitmap.maximum() {
+            assert_eq!(bitmap.alloc(&device), Some(bit));
+        }
+        assert_eq!(bitmap.alloc(&device), None);
+        bitmap.dealloc(&device, BLOCK_BITS + 3);
+        assert_eq!(bitmap.alloc(&device), Some(BLOCK_BITS + 3));
+    }
+}
@@ -0,0 +1,60 @@
+use core::any::Any;
+/// Trait for block devices
+/// which reads and writes data in the unit of blocks
+pub trait BlockDevice: Send + Sync + Any {
+    ///Read data form block to buffer
+    fn read_block(&self, block_id: usize, buf: &mut [u8]);
+    ///Write data from buffer to block
+    fn write_block(&self, block_id: usize, buf: &[u8]);
+}
+
+#[cfg(test)]
+pub(crate) mod test_device {
+    use super::BlockDevice;
+    use crate::{EasyFileSystem, BLOCK_SZ};
+    use alloc::{sync::Arc, vec, vec::Vec};
+    use spin::{Mutex, MutexGuard};
+
+    /// A block device in memory
+    struct MemDevice(Mutex<Vec<[u8; BLOCK_SZ]>>);
+
+    impl BlockDevice for MemDevice {
+        fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+            buf.copy_from_slice(&self.0.lock()[block_id]);
+        }
+        fn write_block(&self, block_id: usize, buf: &[u8]) {
+            self.0.lock()[block_id].copy_from_slice(buf);
+        }
+    }
+
+    /// Held by every test, as they share the blocks of the block cache
+    static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+    /// A guard of the test lock, and a zeroed device of `total_blocks`
+    /// blocks in memory
+    pub fn create_device(total_blocks: usize) -> (MutexGuard<'static, ()>, Arc<dyn BlockDevice>) {
+        let guard = TEST_LOCK.lock();
+        let blocks = vec![[0; BLOCK_SZ]; total_blocks];
+        (guard, Arc::new(MemDevice(Mutex::new(blocks))))
+    }
+
+    /// A guard of the test lock, and a file system of `total_blocks` blocks
+    /// in memory, with one block of inode bitmap
+    pub fn create_fs(total_blocks: usize) -> (MutexGuard<'static, ()>, Arc<Mutex<EasyFileSystem>>) {
+        let (guard, device) = create_device(total_blocks);
+        (
+            guard,
+            EasyFileSystem::create(device, total_blocks as u32, 1),
+        )
+    }
+
+    /// Count the free data blocks of `efs`
+    pub fn free_data_blocks(efs: &Arc<Mutex<EasyFileSystem>>) -> usize {
+        let mut efs = efs.lock();
+        let blocks: Vec<u32> = core::iter::from_fn(|| efs.alloc_data()).collect();
+        for block in blocks.iter() {
+            efs.dealloc_data(*block);
+        }
+        blocks.len()
+    }
+}
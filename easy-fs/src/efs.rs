@@ -0,0 +1,242 @@
+use super::{
+    block_cache_sync_all, get_block_cache, Bitmap, BlockDevice, DiskInode, DiskInodeType, Inode,
+    SuperBlock,
+};
+use crate::BLOCK_SZ;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use spin::Mutex;
+///An easy file system on block
+pub struct EasyFileSystem {
+    ///Real device
+    pub block_device: Arc<dyn BlockDevice>,
+    ///Inode bitmap
+    pub inode_bitmap: Bitmap,
+    ///Data bitmap
+    pub data_bitmap: Bitmap,
+    inode_area_start_block: u32,
+    data_area_start_block: u32,
+    /// Number of data blocks, fewer than the data bitmap has bits for
+    data_area_blocks: u32,
+    /// Number of [`Inode`]s in use for each inode id. An inode whose last
+    /// link is removed is only freed once none is left.
+    open_inodes: BTreeMap<u32, usize>,
+}
+
+type DataBlock = [u8; BLOCK_SZ];
+impl EasyFileSystem {
+    /// Format a block device of `total_blocks` blocks, with
+    /// `inode_bitmap_blocks` blocks of inode bitmap
+    pub fn create(
+        block_device: Arc<dyn BlockDevice>,
+        total_blocks: u32,
+        inode_bitmap_blocks: u32,
+    ) -> Arc<Mutex<Self>> {
+        // calculate block size of areas & create bitmaps
+        let inode_bitmap = Bitmap::new(1, inode_bitmap_blocks as usize);
+        let inode_num = inode_bitmap.maximum();
+        let inode_area_blocks =
+            (inode_num * core::mem::size_of::<DiskInode>()).div_ceil(BLOCK_SZ) as u32;
+        let inode_total_blocks = inode_bitmap_blocks + inode_area_blocks;
+        let data_total_blocks = total_blocks - 1 - inode_total_blocks;
+        // a bitmap block tracks 4096 data blocks
+        let data_bitmap_blocks = data_total_blocks.div_ceil(4097);
+        let data_area_blocks = data_total_blocks - data_bitmap_blocks;
+        let data_bitmap = Bitmap::new(
+            (1 + inode_bitmap_blocks + inode_area_blocks) as usize,
+            data_bitmap_blocks as usize,
+        );
+        let mut efs = Self {
+            block_device: Arc::clone(&block_device),
+            inode_bitmap,
+            data_bitmap,
+            inode_area_start_block: 1 + inode_bitmap_blocks,
+            data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+            data_area_blocks,
+            open_inodes: BTreeMap::new(),
+        };
+        // clear all blocks
+        for i in 0..total_blocks {
+            get_block_cache(i as usize, Arc::clone(&block_device))
+                .lock()
+                .modify(0, |data_block: &mut DataBlock| {
+                    for byte in data_block.iter_mut() {
+                        *byte = 0;
+                    }
+                });
+        }
+        // initialize SuperBlock
+        get_block_cache(0, Arc::clone(&block_device)).lock().modify(
+            0,
+            |super_block: &mut SuperBlock| {
+                super_block.initialize(
+                    total_blocks,
+                    inode_bitmap_blocks,
+                    inode_area_blocks,
+                    data_bitmap_blocks,
+                    data_area_blocks,
+                );
+            },
+        );
+        // create a inode for root node "/"
+        assert_eq!(efs.alloc_inode(), Some(0));
+        let (root_inode_block_id, root_inode_offset) = efs.get_disk_inode_pos(0);
+        get_block_cache(root_inode_block_id as usize, Arc::clone(&block_device))
+            .lock()
+            .modify(root_inode_offset, |disk_inode: &mut DiskInode| {
+                disk_inode.initialize(DiskInodeType::Directory);
+            });
+        block_cache_sync_all();
+        Arc::new(Mutex::new(efs))
+    }
+    /// Open a block device as a filesystem
+    pub fn open(block_device: Arc<dyn BlockDevice>) -> Arc<Mutex<Self>> {
+        // read SuperBlock
+        get_block_cache(0, Arc::clone(&block_device))
+            .lock()
+            .read(0, |super_block: &SuperBlock| {
+                assert!(super_block.is_valid(), "Error loading EFS!");
+                let inode_total_blocks =
+                    super_block.inode_bitmap_blocks + super_block.inode_area_blocks;
+                let efs = Self {
+                    block_device,
+                    inode_bitmap: Bitmap::new(1, super_block.inode_bitmap_blocks as usize),
+                    data_bitmap: Bitmap::new(
+                        (1 + inode_total_blocks) as usize,
+                        super_block.data_bitmap_blocks as usize,
+                    ),
+                    inode_area_start_block: 1 + super_block.inode_bitmap_blocks,
+                    data_area_start_block: 1 + inode_total_blocks + super_block.data_bitmap_blocks,
+                    data_area_blocks: super_block.data_area_blocks,
+                    open_inodes: BTreeMap::new(),
+                };
+                Arc::new(Mutex::new(efs))
+            })
+    }
+    /// Get the root inode of the filesystem
+    pub fn root_inode(efs: &Arc<Mutex<Self>>) -> Inode {
+        Inode::new(0, &mut efs.lock(), Arc::clone(efs))
+    }
+    /// Get inode by id
+    pub fn get_disk_inode_pos(&self, inode_id: u32) -> (u32, usize) {
+        let inode_size = core::mem::size_of::<DiskInode>();
+        let inodes_per_block = (BLOCK_SZ / inode_size) as u32;
+        let block_id = self.inode_area_start_block + inode_id / inodes_per_block;
+        (
+            block_id,
+            (inode_id % inodes_per_block) as usize * inode_size,
+        )
+    }
+    /// Get the id of the inode at `block_offset` of block `block_id`
+    pub fn get_inode_id(&self, block_id: u32, block_offset: usize) -> u32 {
+        let inode_size = core::mem::size_of::<DiskInode>();
+        let inodes_per_block = (BLOCK_SZ / inode_size) as u32;
+        (block_id - self.inode_area_start_block) * inodes_per_block
+            + (block_offset / inode_size) as u32
+    }
+    /// Get data block by id
+    pub fn get_data_block_id(&self, data_block_id: u32) -> u32 {
+        self.data_area_start_block + data_block_id
+    }
+    /// Allocate a new inode, or return `None` if every inode is taken
+    pub fn alloc_inode(&mut self) -> Option<u32> {
+        self.inode_bitmap
+            .alloc(&self.block_device)
+            .map(|inode_id| inode_id as u32)
+    }
+
+    /// Deallocate an inode
+    pub fn dealloc_inode(&mut self, inode_id: u32) {
+        self.inode_bitmap
+            .dealloc(&self.block_device, inode_id as usize)
+    }
+
+    /// Allocate a data block, or return `None` if the disk is full
+    pub fn alloc_data(&mut self) -> Option<u32> {
+        let bit = self.data_bitmap.alloc(&self.block_device)?;
+        // the lowest free bit is past the last data block
+        if bit >= self.data_area_blocks as usize {
+            self.data_bitmap.dealloc(&self.block_device, bit);
+            return None;
+        }
+        Some(bit as u32 + self.data_area_start_block)
+    }
+    /// Deallocate a data block
+    pub fn dealloc_data(&mut self, block_id: u32) {
+        get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(0, |data_block: &mut DataBlock| {
+                data_block.iter_mut().for_each(|p| {
+                    *p = 0;
+                })
+            });
+        self.data_bitmap.dealloc(
+            &self.block_device,
+            (block_id - self.data_area_start_block) as usize,
+        )
+    }
+    /// Count one more [`Inode`] in use for inode `inode_id`
+    pub(crate) fn open_inode(&mut self, inode_id: u32) {
+        *self.open_inodes.entry(inode_id).or_insert(0) += 1;
+    }
+    /// Count one less [`Inode`] in use for inode `inode_id`, and return
+    /// whether it was the last one
+    pub(crate) fn close_inode(&mut self, inode_id: u32) -> bool {
+        let count = self.open_inodes.get_mut(&inode_id).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.open_inodes.remove(&inode_id);
+            true
+        } else {
+            false
+        }
+    }
+    /// Whether an [`Inode`] is in use for inode `inode_id`
+    pub(crate) fn is_open(&self, inode_id: u32) -> bool {
+        self.open_inodes.contains_key(&inode_id)
+    }
+    /// Free inode `inode_id` and its data blocks, as it has no link left
+    pub(crate) fn free_inode(&mut self, inode_id: u32) {
+        let (block_id, block_offset) = self.get_disk_inode_pos(inode_id);
+        let data_blocks_dealloc =
+            get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+                .lock()
+                .modify(block_offset, |disk_inode: &mut DiskInode| {
+                    disk_inode.clear_size(&self.block_device)
+                });
+        for data_block in data_blocks_dealloc.into_iter() {
+            self.dealloc_data(data_block);
+        }
+        self.dealloc_inode(inode_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block_dev::test_device::{create_fs, free_data_blocks};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn allocations_fail_once_the_disk_is_full() {
+        let (_guard, efs) = create_fs(1100);
+        let data_blocks = efs.lock().data_area_blocks as usize;
+        assert_eq!(free_data_blocks(&efs), data_blocks);
+        let mut efs = efs.lock();
+        let blocks: Vec<u32> = core::iter::from_fn(|| efs.alloc_data()).collect();
+        assert_eq!(blocks.len(), data_blocks);
+        // every block lies in the data area
+        for block in blocks.iter() {
+            let data_block = *block - efs.data_area_start_block;
+            assert!(data_block < efs.data_area_blocks);
+        }
+        assert_eq!(efs.alloc_data(), None);
+        efs.dealloc_data(blocks[3]);
+        assert_eq!(efs.alloc_data(), Some(blocks[3]));
+        // inode 0 is the root directory
+        let inodes = efs.inode_bitmap.maximum();
+        for inode_id in 1..inodes {
+            assert_eq!(efs.alloc_inode(), Some(inode_id as u32));
+        }
+        assert_eq!(efs.alloc_inode(), None);
+    }
+}
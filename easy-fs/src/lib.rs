@@ -0,0 +1,22 @@
+//! An easy file system isolated from the kernel
+//!
+//! The disk is laid out as a super block, an inode bitmap, an inode area, a
+//! data bitmap and a data area, in this order. Every block is accessed
+//! through the block cache. All files live in a single flat root directory.
+#![no_std]
+#![deny(missing_docs)]
+extern crate alloc;
+mod bitmap;
+mod block_cache;
+mod block_dev;
+mod efs;
+mod layout;
+mod vfs;
+/// Use a block size of 512 bytes
+pub const BLOCK_SZ: usize = 512;
+use bitmap::Bitmap;
+use block_cache::{block_cache_sync_all, get_block_cache};
+pub use block_dev::BlockDevice;
+pub use efs::EasyFileSystem;
+use layout::*;
+pub use vfs::Inode;
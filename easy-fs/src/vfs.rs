@@ -0,0 +1,478 @@
+use super::{
+    block_cache_sync_all, get_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType,
+    EasyFileSystem, BLOCK_SZ, DIRENT_SZ,
+};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use spin::{Mutex, MutexGuard};
+/// Virtual filesystem layer over easy-fs
+///
+/// An inode unlinked while an `Inode` of it is still in use, like a file
+/// still open, is freed when the last such `Inode` is dropped.
+pub struct Inode {
+    block_id: usize,
+    block_offset: usize,
+    fs: Arc<Mutex<EasyFileSystem>>,
+    block_device: Arc<dyn BlockDevice>,
+}
+
+impl Inode {
+    /// Create a vfs inode for inode `inode_id` of `fs`, which `efs` is
+    /// locked into
+    pub(crate) fn new(
+        inode_id: u32,
+        efs: &mut EasyFileSystem,
+        fs: Arc<Mutex<EasyFileSystem>>,
+    ) -> Self {
+        let (block_id, block_offset) = efs.get_disk_inode_pos(inode_id);
+        efs.open_inode(inode_id);
+        Self {
+            block_id: block_id as usize,
+            block_offset,
+            fs,
+            block_device: Arc::clone(&efs.block_device),
+        }
+    }
+    /// Call a function over a disk inode to read it
+    fn read_disk_inode<V>(&self, f: impl FnOnce(&DiskInode) -> V) -> V {
+        get_block_cache(self.block_id, Arc::clone(&self.block_device))
+            .lock()
+            .read(self.block_offset, f)
+    }
+    /// Call a function over a disk inode to modify it
+    fn modify_disk_inode<V>(&self, f: impl FnOnce(&mut DiskInode) -> V) -> V {
+        get_block_cache(self.block_id, Arc::clone(&self.block_device))
+            .lock()
+            .modify(self.block_offset, f)
+    }
+    /// Find inode under a disk inode by name
+    fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
+        // assert it is a directory
+        assert!(disk_inode.is_dir());
+        let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+        let mut dirent = DirEntry::empty();
+        for i in 0..file_count {
+            assert_eq!(
+                disk_inode.read_at(DIRENT_SZ * i, dirent.as_bytes_mut(), &self.block_device,),
+                DIRENT_SZ,
+            );
+            if dirent.name() == name {
+                return Some(dirent.inode_id());
+            }
+        }
+        None
+    }
+    /// Find inode under current inode by name
+    pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
+        let mut fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            self.find_inode_id(name, disk_inode)
+                .map(|inode_id| Arc::new(Self::new(inode_id, &mut fs, self.fs.clone())))
+        })
+    }
+    /// Increase the size of a disk inode, leaving it as it is and returning
+    /// `None` if there are not enough free data blocks
+    fn increase_size(
+        &self,
+        new_size: u32,
+        disk_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> Option<()> {
+        if new_size < disk_inode.size {
+            return Some(());
+        }
+        let blocks_needed = disk_inode.blocks_num_needed(new_size);
+        let mut v: Vec<u32> = Vec::new();
+        for _ in 0..blocks_needed {
+            match fs.alloc_data() {
+                Some(block) => v.push(block),
+                None => {
+                    for block in v {
+                        fs.dealloc_data(block);
+                    }
+                    return None;
+                }
+            }
+        }
+        disk_inode.increase_size(new_size, v, &self.block_device);
+        Some(())
+    }
+    /// Append a directory entry to a directory disk inode, returning `None`
+    /// if the disk is full
+    fn append_dirent(
+        &self,
+        name: &str,
+        inode_id: u32,
+        disk_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> Option<()> {
+        let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+        let new_size = (file_count + 1) * DIRENT_SZ;
+        // increase size
+        self.increase_size(new_size as u32, disk_inode, fs)?;
+        // write dirent
+        let dirent = DirEntry::new(name, inode_id);
+        disk_inode.write_at(
+            file_count * DIRENT_SZ,
+            dirent.as_bytes(),
+            &self.block_device,
+        );
+        Some(())
+    }
+    /// Read all directory entries of a directory disk inode
+    fn read_dirents(&self, disk_inode: &DiskInode) -> Vec<DirEntry> {
+        let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+        let mut v: Vec<DirEntry> = Vec::new();
+        for i in 0..file_count {
+            let mut dirent = DirEntry::empty();
+            assert_eq!(
+                disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device,),
+                DIRENT_SZ,
+            );
+            v.push(dirent);
+        }
+        v
+    }
+    /// Create inode under current inode by name, failing if the name is
+    /// taken or does not fit in a directory entry, or if there is no free
+    /// inode or data block left for it
+    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        if !DirEntry::fits(name) {
+            return None;
+        }
+        let mut fs = self.fs.lock();
+        let op = |root_inode: &DiskInode| {
+            // assert it is a directory
+            assert!(root_inode.is_dir());
+            // has the file been created?
+            self.find_inode_id(name, root_inode)
+        };
+        if self.read_disk_inode(op).is_some() {
+            return None;
+        }
+        // create a new file
+        // alloc a inode with an indirect block
+        let new_inode_id = fs.alloc_inode()?;
+        // initialize inode
+        let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
+        get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
+                new_inode.initialize(DiskInodeType::File);
+            });
+        let appended = self.modify_disk_inode(|root_inode| {
+            self.append_dirent(name, new_inode_id, root_inode, &mut fs)
+        });
+        if appended.is_none() {
+            fs.dealloc_inode(new_inode_id);
+            return None;
+        }
+        block_cache_sync_all();
+        // return inode
+        Some(Arc::new(Self::new(new_inode_id, &mut fs, self.fs.clone())))
+        // release efs lock automatically by compiler
+    }
+    /// Add `new_name` under current inode as a hard link to the inode named
+    /// `old_name`, failing if `old_name` does not exist, `new_name` is taken
+    /// or the disk is full
+    pub fn link(&self, old_name: &str, new_name: &str) -> bool {
+        if !DirEntry::fits(new_name) {
+            return false;
+        }
+        let mut fs = self.fs.lock();
+        let (old_inode_id, new_inode_id) = self.read_disk_inode(|root_inode| {
+            (
+                self.find_inode_id(old_name, root_inode),
+                self.find_inode_id(new_name, root_inode),
+            )
+        });
+        let inode_id = match (old_inode_id, new_inode_id) {
+            (Some(inode_id), None) => inode_id,
+            _ => return false,
+        };
+        let appended = self.modify_disk_inode(|root_inode| {
+            self.append_dirent(new_name, inode_id, root_inode, &mut fs)
+        });
+        if appended.is_none() {
+            return false;
+        }
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(block_offset, |disk_inode: &mut DiskInode| {
+                disk_inode.nlink += 1;
+            });
+        block_cache_sync_all();
+        true
+    }
+    /// Remove `name` from current inode. The inode it refers to is freed
+    /// along with its data once its last link is gone and no `Inode` of it
+    /// is in use.
+    pub fn unlink(&self, name: &str) -> bool {
+        let mut fs = self.fs.lock();
+        let mut dirents = self.read_disk_inode(|root_inode| self.read_dirents(root_inode));
+        let inode_id = match dirents.iter().position(|dirent| dirent.name() == name) {
+            Some(pos) => dirents.remove(pos).inode_id(),
+            None => return false,
+        };
+        // write the remaining entries back to a shrunk directory
+        self.modify_disk_inode(|root_inode| {
+            for data_block in root_inode.clear_size(&self.block_device) {
+                fs.dealloc_data(data_block);
+            }
+            self.increase_size((dirents.len() * DIRENT_SZ) as u32, root_inode, &mut fs)
+                .expect("a directory shrinks into the blocks it freed");
+            for (i, dirent) in dirents.iter().enumerate() {
+                root_inode.write_at(i * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+            }
+        });
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        let nlink = get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(block_offset, |disk_inode: &mut DiskInode| {
+                disk_inode.nlink -= 1;
+                disk_inode.nlink
+            });
+        if nlink == 0 && !fs.is_open(inode_id) {
+            fs.free_inode(inode_id);
+        }
+        block_cache_sync_all();
+        true
+    }
+    /// Get the id of current inode
+    pub fn inode_id(&self) -> u32 {
+        self.fs
+            .lock()
+            .get_inode_id(self.block_id as u32, self.block_offset)
+    }
+    /// Get the number of links to current inode
+    pub fn nlink(&self) -> u32 {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.nlink)
+    }
+    /// Whether current inode is a directory
+    pub fn is_dir(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_dir())
+    }
+    /// List inodes under current inode
+    pub fn ls(&self) -> Vec<String> {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            self.read_dirents(disk_inode)
+                .iter()
+                .map(|dirent| String::from(dirent.name()))
+                .collect()
+        })
+    }
+    /// Read data from current inode
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
+    }
+    /// Write data to current inode, growing it as needed. Return how many
+    /// bytes were written, fewer than `buf.len()` if the disk got full.
+    pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        let mut fs = self.fs.lock();
+        let size = self.modify_disk_inode(|disk_inode| {
+            let end = offset + buf.len();
+            if self
+                .increase_size(end as u32, disk_inode, &mut fs)
+                .is_none()
+            {
+                // grow block by block for as long as there are free blocks
+                while (disk_inode.size as usize) < end {
+                    let next = (disk_inode.size as usize / BLOCK_SZ + 1) * BLOCK_SZ;
+                    if self
+                        .increase_size(next.min(end) as u32, disk_inode, &mut fs)
+                        .is_none()
+                    {
+                        break;
+                    }
+                }
+            }
+            if offset >= disk_inode.size as usize {
+                return 0;
+            }
+            disk_inode.write_at(offset, buf, &self.block_device)
+        });
+        block_cache_sync_all();
+        size
+    }
+    /// Clear the data in current inode
+    pub fn clear(&self) {
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            let size = disk_inode.size;
+            let data_blocks_dealloc = disk_inode.clear_size(&self.block_device);
+            assert!(data_blocks_dealloc.len() == DiskInode::total_blocks(size) as usize);
+            for data_block in data_blocks_dealloc.into_iter() {
+                fs.dealloc_data(data_block);
+            }
+        });
+        block_cache_sync_all();
+    }
+}
+
+impl Drop for Inode {
+    /// Free the inode if it was unlinked while in use and this was the last
+    /// `Inode` of it.
+    ///
+    /// This takes the `fs` lock, so an `Inode` must never be dropped while
+    /// that lock is held, as in a closure run under it: the lock is not
+    /// reentrant and would spin forever.
+    fn drop(&mut self) {
+        let mut fs = self.fs.lock();
+        let inode_id = fs.get_inode_id(self.block_id as u32, self.block_offset);
+        if fs.close_inode(inode_id) && self.read_disk_inode(|disk_inode| disk_inode.nlink) == 0 {
+            fs.free_inode(inode_id);
+            block_cache_sync_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block_dev::test_device::{create_fs, free_data_blocks};
+    use crate::{EasyFileSystem, BLOCK_SZ};
+    use alloc::{format, vec, vec::Vec};
+
+    #[test]
+    fn created_files_are_found_by_name() {
+        let (_guard, efs) = create_fs(2048);
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        assert!(root_inode.is_dir());
+        let filea = root_inode.create("filea").unwrap();
+        let fileb = root_inode.create("fileb").unwrap();
+        assert!(!filea.is_dir());
+        assert_ne!(filea.inode_id(), fileb.inode_id());
+        assert!(root_inode.create("filea").is_none());
+        assert!(root_inode.create(&"a".repeat(28)).is_none());
+        assert_eq!(root_inode.ls(), ["filea", "fileb"]);
+        assert_eq!(
+            root_inode.find("fileb").unwrap().inode_id(),
+            fileb.inode_id()
+        );
+        assert!(root_inode.find("filec").is_none());
+    }
+
+    #[test]
+    fn data_spans_direct_and_indirect_blocks() {
+        let (_guard, efs) = create_fs(2048);
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let file = root_inode.create("file").unwrap();
+        let free = free_data_blocks(&efs);
+        // past the 27 direct blocks and the 128 blocks of indirect1, into
+        // two blocks of indirect2
+        let data_blocks = 27 + 128 + 130;
+        let data: Vec<u8> = (0..data_blocks * BLOCK_SZ)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        assert_eq!(file.write_at(0, &data), data.len());
+        // with the indirect1 block, the indirect2 block and two blocks of it
+        assert_eq!(free_data_blocks(&efs), free - data_blocks - 4);
+        let mut buffer = vec![0u8; data.len() + BLOCK_SZ];
+        assert_eq!(file.read_at(0, &mut buffer), data.len());
+        assert_eq!(buffer[..data.len()], data[..]);
+        // reads and writes within a block, across block boundaries
+        let offset = (27 + 128) * BLOCK_SZ - 3;
+        assert_eq!(file.write_at(offset, b"boundary"), 8);
+        assert_eq!(file.read_at(offset, &mut buffer[..8]), 8);
+        assert_eq!(&buffer[..8], b"boundary");
+        file.clear();
+        assert_eq!(file.read_at(0, &mut buffer), 0);
+        assert_eq!(free_data_blocks(&efs), free);
+    }
+
+    #[test]
+    fn writes_come_up_short_on_a_full_disk() {
+        // the inodes take most of the blocks, leaving less than 100 for data
+        let (_guard, efs) = create_fs(1100);
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let filea = root_inode.create("filea").unwrap();
+        let data = [0x5au8; 100 * BLOCK_SZ];
+        let written = filea.write_at(0, &data);
+        assert!(written > 0 && written < data.len());
+        assert_eq!(free_data_blocks(&efs), 0);
+        assert_eq!(filea.write_at(written, &data), 0);
+        let mut buffer = [0u8; 100 * BLOCK_SZ];
+        assert_eq!(filea.read_at(0, &mut buffer), written);
+        assert_eq!(buffer[..written], data[..written]);
+        // files can be created until the block of the directory is full
+        let mut files = 1;
+        while root_inode.create(&format!("file{}", files)).is_some() {
+            files += 1;
+        }
+        assert_eq!(files, BLOCK_SZ / 32);
+        assert!(!root_inode.link("filea", "filez"));
+        // freeing the data makes room again
+        filea.clear();
+        assert!(root_inode.create("last").is_some());
+        assert!(root_inode.link("filea", "filez"));
+    }
+
+    #[test]
+    fn inodes_live_until_their_last_link_is_gone() {
+        let (_guard, efs) = create_fs(2048);
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let free = free_data_blocks(&efs);
+        let filea = root_inode.create("filea").unwrap();
+        let inode_id = filea.inode_id();
+        assert_eq!(filea.nlink(), 1);
+        assert_eq!(filea.write_at(0, &[1; 2 * BLOCK_SZ]), 2 * BLOCK_SZ);
+        drop(filea);
+        assert!(root_inode.link("filea", "fileb"));
+        assert!(!root_inode.link("filea", "fileb"));
+        assert!(!root_inode.link("filec", "filed"));
+        let fileb = root_inode.find("fileb").unwrap();
+        assert_eq!(fileb.inode_id(), inode_id);
+        assert_eq!(fileb.nlink(), 2);
+        drop(fileb);
+        assert!(root_inode.unlink("filea"));
+        assert!(!root_inode.unlink("filea"));
+        assert_eq!(root_inode.ls(), ["fileb"]);
+        let fileb = root_inode.find("fileb").unwrap();
+        assert_eq!(fileb.nlink(), 1);
+        let mut buffer = [0u8; BLOCK_SZ];
+        assert_eq!(fileb.read_at(BLOCK_SZ, &mut buffer), BLOCK_SZ);
+        assert_eq!(buffer, [1; BLOCK_SZ]);
+        drop(fileb);
+        // the last link takes the inode and its data along
+        assert!(root_inode.unlink("fileb"));
+        assert!(root_inode.ls().is_empty());
+        assert_eq!(free_data_blocks(&efs), free);
+        let filec = root_inode.create("filec").unwrap();
+        assert_eq!(filec.inode_id(), inode_id);
+        assert_eq!(filec.read_at(0, &mut buffer), 0);
+    }
+
+    #[test]
+    fn unlinked_inodes_are_freed_on_last_drop() {
+        let (_guard, efs) = create_fs(2048);
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let free = free_data_blocks(&efs);
+        let file = root_inode.create("file").unwrap();
+        let inode_id = file.inode_id();
+        assert_eq!(file.write_at(0, &[7; 3 * BLOCK_SZ]), 3 * BLOCK_SZ);
+        let other = root_inode.find("file").unwrap();
+        // the root directory took a block for its entry
+        let used = free - free_data_blocks(&efs);
+        assert_eq!(used, 3 + 1);
+        assert!(root_inode.unlink("file"));
+        assert!(root_inode.find("file").is_none());
+        // still open, so its blocks stay allocated and readable
+        assert_eq!(file.nlink(), 0);
+        assert_eq!(free_data_blocks(&efs), free - 3);
+        let mut buffer = [0u8; BLOCK_SZ];
+        assert_eq!(other.read_at(2 * BLOCK_SZ, &mut buffer), BLOCK_SZ);
+        assert_eq!(buffer, [7; BLOCK_SZ]);
+        drop(file);
+        assert_eq!(free_data_blocks(&efs), free - 3);
+        assert_ne!(root_inode.create("next").unwrap().inode_id(), inode_id);
+        // the last `Inode` frees the blocks and the inode
+        drop(other);
+        assert_eq!(free_data_blocks(&efs), free - 1);
+        assert_eq!(root_inode.create("last").unwrap().inode_id(), inode_id);
+    }
+}
@@ -1,19 +1,40 @@
-use std::fs::{read_dir, File};
-use std::io::{Result, Write};
+//! Pack the user apps, and the files under `user/rootfs`, into an initramfs
+//! in cpio newc format that is embedded into the kernel.
+//!
+//! Also embed the kernel symbol table named by `KERNEL_SYMS`, which `make`
+//! generates from a first build of the kernel, or an empty table without it.
+use std::fs::{read, read_dir};
+use std::io::Result;
+use std::path::Path;
 
 fn main() {
     println!("cargo:rerun-if-changed=../user/src/");
     println!("cargo:rerun-if-changed={}", TARGET_PATH);
-    insert_app_data().unwrap();
+    println!("cargo:rerun-if-changed={}", ROOTFS_PATH);
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(
+        Path::new(&out_dir).join("initramfs.cpio"),
+        pack_initramfs().unwrap(),
+    )
+    .unwrap();
+    println!("cargo:rerun-if-env-changed=KERNEL_SYMS");
+    let symbols = match std::env::var("KERNEL_SYMS") {
+        Ok(path) => {
+            println!("cargo:rerun-if-changed={}", path);
+            read(path).unwrap()
+        }
+        Err(_) => Vec::new(),
+    };
+    std::fs::write(Path::new(&out_dir).join("kernel.sym"), symbols).unwrap();
 }
 
 static TARGET_PATH: &str = "../user/target/riscv64gc-unknown-none-elf/release/";
+static ROOTFS_PATH: &str = "../user/rootfs/";
 
-fn insert_app_data() -> Result<()> {
-    let mut f = File::create("src/link_app.S").unwrap();
-    let mut apps: Vec<_> = read_dir("../user/src/bin")
-        .unwrap()
-        .into_iter()
+fn pack_initramfs() -> Result<Vec<u8>> {
+    let mut archive = Vec::new();
+    let mut ino = 0;
+    let mut apps: Vec<_> = read_dir("../user/src/bin")?
         .map(|dir_entry| {
             let mut name_with_ext = dir_entry.unwrap().file_name().into_string().unwrap();
             name_with_ext.drain(name_with_ext.find('.').unwrap()..name_with_ext.len());
@@ -21,36 +42,79 @@ fn insert_app_data() -> Result<()> {
         })
         .collect();
     apps.sort();
+    for app in apps {
+        match read(format!("{}{}", TARGET_PATH, app)) {
+            Ok(data) => {
+                println!("bin/{}", app);
+                ino += 1;
+                write_entry(&mut archive, ino, 0o100755, &format!("bin/{}", app), &data);
+            }
+            Err(_) => println!("cargo:warning=app {} has not been built", app),
+        }
+    }
+    let mut files = Vec::new();
+    if Path::new(ROOTFS_PATH).is_dir() {
+        collect_files(Path::new(ROOTFS_PATH), "", &mut files)?;
+    }
+    files.sort();
+    for (path, host_path) in files {
+        println!("{}", path);
+        ino += 1;
+        write_entry(&mut archive, ino, 0o100644, &path, &read(host_path)?);
+    }
+    write_entry(&mut archive, 0, 0, "TRAILER!!!", &[]);
+    Ok(archive)
+}
 
-    writeln!(
-        f,
-        r#"
-    .align 3
-    .section .data
-    .global _num_app
-_num_app:
-    .quad {}"#,
-        apps.len()
-    )?;
+/// Collect the regular files under `dir` as (archive path, host path)
+fn collect_files(
+    dir: &Path,
+    prefix: &str,
+    files: &mut Vec<(String, std::path::PathBuf)>,
+) -> Result<()> {
+    for dir_entry in read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let path = format!("{}{}", prefix, dir_entry.file_name().into_string().unwrap());
+        if dir_entry.file_type()?.is_dir() {
+            collect_files(&dir_entry.path(), &format!("{}/", path), files)?;
+        } else {
+            files.push((path, dir_entry.path()));
+        }
+    }
+    Ok(())
+}
 
-    for i in 0..apps.len() {
-        writeln!(f, r#"    .quad app_{}_start"#, i)?;
+/// Append a newc entry: the header, the name and the data, each of the last
+/// two padded to a multiple of 4 bytes
+fn write_entry(archive: &mut Vec<u8>, ino: u32, mode: u32, name: &str, data: &[u8]) {
+    let fields = [
+        ino,
+        mode,
+        0, // uid
+        0, // gid
+        1, // nlink
+        0, // mtime
+        data.len() as u32,
+        0, // devmajor
+        0, // devminor
+        0, // rdevmajor
+        0, // rdevminor
+        name.len() as u32 + 1,
+        0, // check
+    ];
+    archive.extend_from_slice(b"070701");
+    for field in fields {
+        archive.extend_from_slice(format!("{:08x}", field).as_bytes());
     }
-    writeln!(f, r#"    .quad app_{}_end"#, apps.len() - 1)?;
+    archive.extend_from_slice(name.as_bytes());
+    archive.push(0);
+    pad4(archive);
+    archive.extend_from_slice(data);
+    pad4(archive);
+}
 
-    for (idx, app) in apps.iter().enumerate() {
-        println!("app_{}: {}", idx, app);
-        writeln!(
-            f,
-            r#"
-    .section .data
-    .global app_{0}_start
-    .global app_{0}_end
-app_{0}_start:
-    .incbin "{2}{1}.bin"
-app_{0}_end:"#,
-            idx, app, TARGET_PATH
-        )?;
+fn pad4(archive: &mut Vec<u8>) {
+    while archive.len() % 4 != 0 {
+        archive.push(0);
     }
-    Ok(())
 }
@@ -0,0 +1,13 @@
+#!/bin/sh
+
+# Cargo runner of the kernel test build, see `make test`
+# Argument1: The bootloader
+# Argument2: The filesystem image
+# Argument3: The kernel test ELF, appended by cargo
+exec qemu-system-riscv64 \
+    -machine virt \
+    -nographic \
+    -bios $1 \
+    -device loader,file=$3 \
+    -drive file=$2,if=none,format=raw,id=x0 \
+    -device virtio-blk-device,drive=x0,bus=virtio-mmio-bus.0
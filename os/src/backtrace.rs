@@ -0,0 +1,72 @@
+//! Kernel backtraces
+//!
+//! The kernel is built with frame pointers, so every frame keeps the return
+//! address at `fp - 8` and the frame pointer of its caller at `fp - 16`.
+//!
+//! Return addresses are named through a symbol table of `<address> <name>`
+//! lines sorted by address. `make` generates it with `nm` from a first build
+//! of the kernel and embeds it in a second one, see `build.rs`. The table is
+//! only reached through a volatile read of [`KERNEL_SYMBOLS`], so its size
+//! cannot change the code of the second build.
+
+use core::arch::asm;
+
+/// Symbol table of the kernel functions, empty when built without `make`
+static KERNEL_SYMBOLS: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/kernel.sym"));
+
+/// Stop walking after this many frames, in case the chain loops
+const MAX_DEPTH: usize = 32;
+
+/// Print the call stack of the caller, innermost first
+pub fn print_backtrace() {
+    extern "C" {
+        fn stext();
+        fn etext();
+        fn skernel();
+        fn ekernel();
+    }
+    let mut fp: usize;
+    unsafe {
+        asm!("mv {}, fp", out(reg) fp);
+    }
+    println!("Backtrace:");
+    for depth in 0..MAX_DEPTH {
+        // frames live on kernel stacks, and the chain ends where a frame
+        // pointer comes from outside the kernel, like the SBI or an app
+        if fp % 8 != 0 || fp < skernel as usize + 16 || fp > ekernel as usize {
+            break;
+        }
+        let ra = unsafe { *((fp - 8) as *const usize) };
+        if !(stext as usize..etext as usize).contains(&ra) {
+            break;
+        }
+        match lookup(ra) {
+            Some((name, offset)) => {
+                println!("  #{} {:#x} {}+{:#x}", depth, ra, name, offset);
+            }
+            None => {
+                println!("  #{} {:#x}", depth, ra);
+            }
+        }
+        fp = unsafe { *((fp - 16) as *const usize) };
+    }
+}
+
+/// Find the function containing `addr`, and the offset of `addr` in it.
+/// Of several names for one address, like `_start` and `stext`, the first
+/// one is taken.
+fn lookup(addr: usize) -> Option<(&'static str, usize)> {
+    let symbols = unsafe { core::ptr::read_volatile(&KERNEL_SYMBOLS) };
+    let mut found: Option<(&str, usize)> = None;
+    for line in core::str::from_utf8(symbols).ok()?.lines() {
+        let (start, name) = line.split_once(' ')?;
+        let start = usize::from_str_radix(start, 16).ok()?;
+        if start > addr {
+            break;
+        }
+        if found.map_or(true, |(_, found_start)| found_start < start) {
+            found = Some((name, start));
+        }
+    }
+    found.map(|(name, start)| (name, addr - start))
+}
@@ -1,3 +1,8 @@
 //! Constants used in rCore for qemu
 
 pub const CLOCK_FREQ: usize = 12500000;
+
+/// MMIO base of `virtio-mmio@10001000`, the first virtio-mmio slot of QEMU virt
+pub const VIRTIO0: usize = 0x10001000;
+
+pub type BlockDeviceImpl = crate::drivers::block::VirtIOBlock;
@@ -2,9 +2,14 @@
 
 pub const USER_STACK_SIZE: usize = 4096;
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
-pub const MAX_APP_NUM: usize = 4;
-pub const APP_BASE_ADDRESS: usize = 0x80400000;
+pub const KERNEL_HEAP_SIZE: usize = 0x20_0000;
+pub const MAX_APP_NUM: usize = 32;
+pub const APP_BASE_ADDRESS: usize = 0x80800000;
 pub const APP_SIZE_LIMIT: usize = 0x20000;
+pub const PAGE_SIZE: usize = 0x1000;
+/// Start of the region handed out to apps by `sys_mmap`, after the app slots
+pub const USER_MMAP_BASE: usize = APP_BASE_ADDRESS + MAX_APP_NUM * APP_SIZE_LIMIT;
+pub const USER_MMAP_SIZE: usize = 0x40_0000;
 
 /*
 #[cfg(feature = "board_k210")]
@@ -18,6 +18,13 @@ pub fn print(args: fmt::Arguments) {
     Stdout.write_fmt(args).unwrap();
 }
 
+/// put raw bytes to the console, whether they are text or not
+pub fn put_bytes(bytes: &[u8]) {
+    for &byte in bytes {
+        console_putchar(byte as usize);
+    }
+}
+
 /// print string macro
 #[macro_export]
 macro_rules! print {
@@ -0,0 +1,47 @@
+//! Block devices
+//!
+//! A block device is accessed in blocks of [`BLOCK_SZ`] bytes through the
+//! [`BlockDevice`] trait of easy-fs. The only one we have is the virtio block
+//! device that QEMU attaches `fs.img` to.
+
+mod virtio_blk;
+
+pub use virtio_blk::VirtIOBlock;
+
+use crate::board::BlockDeviceImpl;
+use alloc::sync::Arc;
+use easy_fs::BlockDevice;
+use lazy_static::*;
+
+lazy_static! {
+    /// The block device of the board
+    pub static ref BLOCK_DEVICE: Arc<dyn BlockDevice> = Arc::new(BlockDeviceImpl::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BLOCK_DEVICE;
+    use easy_fs::BLOCK_SZ;
+
+    /// Magic number at the start of the super block of easy-fs
+    const EFS_MAGIC: u32 = 0x3b800001;
+
+    #[test_case]
+    fn fs_img_is_read_and_written() {
+        let mut block = [0u8; BLOCK_SZ];
+        BLOCK_DEVICE.read_block(0, &mut block);
+        let magic = u32::from_le_bytes(block[..4].try_into().unwrap());
+        assert_eq!(magic, EFS_MAGIC, "block 0 of fs.img is no super block");
+        // write a pattern over the unused second half of the block, read it
+        // back, and restore the block before checking
+        let saved = block;
+        for (i, byte) in block.iter_mut().enumerate().skip(BLOCK_SZ / 2) {
+            *byte = i as u8;
+        }
+        BLOCK_DEVICE.write_block(0, &block);
+        let mut read_back = [0u8; BLOCK_SZ];
+        BLOCK_DEVICE.read_block(0, &mut read_back);
+        BLOCK_DEVICE.write_block(0, &saved);
+        assert_eq!(read_back, block);
+    }
+}
@@ -0,0 +1,286 @@
+//! Polling driver of the virtio block device over virtio-mmio
+//!
+//! The kernel runs on physical addresses, so buffers are handed to the
+//! device as they are. The queue, request header and status byte live in a
+//! zeroed heap allocation made before the device learns their addresses,
+//! which therefore never change, and which is too large for a kernel stack
+//! anyway. A single request is in flight at a time: it always
+//! takes the first three descriptors of the queue (request header, data,
+//! status), and the driver spins until the device has used them.
+
+use crate::board::VIRTIO0;
+use crate::sync::UPSafeCell;
+use alloc::alloc::{alloc_zeroed, handle_alloc_error, Layout};
+use alloc::boxed::Box;
+use core::ptr::{addr_of, addr_of_mut, read_volatile, write_volatile};
+use core::sync::atomic::{fence, Ordering};
+use easy_fs::{BlockDevice, BLOCK_SZ};
+
+const MAGIC_VALUE: usize = 0x000;
+const VERSION: usize = 0x004;
+const DEVICE_ID: usize = 0x008;
+const DEVICE_FEATURES_SEL: usize = 0x014;
+const DEVICE_FEATURES: usize = 0x010;
+const DRIVER_FEATURES: usize = 0x020;
+const DRIVER_FEATURES_SEL: usize = 0x024;
+const GUEST_PAGE_SIZE: usize = 0x028;
+const QUEUE_SEL: usize = 0x030;
+const QUEUE_NUM_MAX: usize = 0x034;
+const QUEUE_NUM: usize = 0x038;
+const QUEUE_ALIGN: usize = 0x03c;
+const QUEUE_PFN: usize = 0x040;
+const QUEUE_READY: usize = 0x044;
+const QUEUE_NOTIFY: usize = 0x050;
+const INTERRUPT_STATUS: usize = 0x060;
+const INTERRUPT_ACK: usize = 0x064;
+const STATUS: usize = 0x070;
+const QUEUE_DESC_LOW: usize = 0x080;
+const QUEUE_DESC_HIGH: usize = 0x084;
+const QUEUE_DRIVER_LOW: usize = 0x090;
+const QUEUE_DRIVER_HIGH: usize = 0x094;
+const QUEUE_DEVICE_LOW: usize = 0x0a0;
+const QUEUE_DEVICE_HIGH: usize = 0x0a4;
+
+const STATUS_ACKNOWLEDGE: u32 = 1;
+const STATUS_DRIVER: u32 = 2;
+const STATUS_DRIVER_OK: u32 = 4;
+const STATUS_FEATURES_OK: u32 = 8;
+
+/// "virt" in little endian
+const VIRTIO_MAGIC: u32 = 0x74726976;
+const VIRTIO_DEVICE_BLOCK: u32 = 2;
+/// Feature bit 32 (bit 0 of the second feature word), which modern (version 2)
+/// devices want the driver to accept
+const VIRTIO_F_VERSION_1: u32 = 1;
+
+const VIRTQ_DESC_F_NEXT: u16 = 1;
+const VIRTQ_DESC_F_WRITE: u16 = 2;
+
+const VIRTIO_BLK_T_IN: u32 = 0;
+const VIRTIO_BLK_T_OUT: u32 = 1;
+const VIRTIO_BLK_S_OK: u8 = 0;
+
+const PAGE_SIZE: usize = 4096;
+/// Number of descriptors of the request queue
+const QUEUE_SIZE: usize = 8;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Descriptor {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+#[repr(C)]
+struct AvailRing {
+    flags: u16,
+    idx: u16,
+    ring: [u16; QUEUE_SIZE],
+    used_event: u16,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct UsedElem {
+    id: u32,
+    len: u32,
+}
+
+#[repr(C)]
+struct UsedRing {
+    flags: u16,
+    idx: u16,
+    ring: [UsedElem; QUEUE_SIZE],
+    avail_event: u16,
+}
+
+/// The part of the queue written by the driver
+#[repr(C, align(4096))]
+struct DriverArea {
+    desc: [Descriptor; QUEUE_SIZE],
+    avail: AvailRing,
+}
+
+/// The part of the queue written by the device
+#[repr(C, align(4096))]
+struct DeviceArea {
+    used: UsedRing,
+}
+
+/// A queue laid out as legacy devices expect: the used ring starts on the
+/// page following the descriptor table and the available ring
+#[repr(C)]
+struct VirtQueue {
+    driver: DriverArea,
+    device: DeviceArea,
+}
+
+#[repr(C)]
+struct BlkReqHeader {
+    req_type: u32,
+    reserved: u32,
+    sector: u64,
+}
+
+/// Driver state behind the lock, shared with the device, for which all
+/// zeroes is the initial state
+struct VirtIOBlockInner {
+    queue: VirtQueue,
+    /// `used.idx` we have seen so far
+    last_used_idx: u16,
+    header: BlkReqHeader,
+    status: u8,
+}
+
+/// Virtio block device at [`VIRTIO0`]
+pub struct VirtIOBlock {
+    inner: UPSafeCell<Box<VirtIOBlockInner>>,
+}
+
+fn read_reg(offset: usize) -> u32 {
+    unsafe { read_volatile((VIRTIO0 + offset) as *const u32) }
+}
+
+fn write_reg(offset: usize, value: u32) {
+    unsafe { write_volatile((VIRTIO0 + offset) as *mut u32, value) }
+}
+
+impl VirtIOBlock {
+    /// Reset and set up the device with a single request queue
+    pub fn new() -> Self {
+        assert_eq!(read_reg(MAGIC_VALUE), VIRTIO_MAGIC, "no virtio device");
+        let version = read_reg(VERSION);
+        assert!(version == 1 || version == 2, "unknown virtio version");
+        assert_eq!(
+            read_reg(DEVICE_ID),
+            VIRTIO_DEVICE_BLOCK,
+            "not a virtio block device, is fs.img attached?"
+        );
+        // the device is given the addresses of the queue, so build it in
+        // place rather than on the stack
+        let layout = Layout::new::<VirtIOBlockInner>();
+        let inner = unsafe {
+            let ptr = alloc_zeroed(layout) as *mut VirtIOBlockInner;
+            if ptr.is_null() {
+                handle_alloc_error(layout);
+            }
+            Box::from_raw(ptr)
+        };
+        let this = Self {
+            inner: unsafe { UPSafeCell::new(inner) },
+        };
+        this.init_device(version);
+        this
+    }
+
+    fn init_device(&self, version: u32) {
+        // reset, then tell the device we found it and know how to drive it
+        write_reg(STATUS, 0);
+        let mut status = STATUS_ACKNOWLEDGE | STATUS_DRIVER;
+        write_reg(STATUS, status);
+        // we need no optional feature
+        write_reg(DEVICE_FEATURES_SEL, 0);
+        let _ = read_reg(DEVICE_FEATURES);
+        write_reg(DRIVER_FEATURES_SEL, 0);
+        write_reg(DRIVER_FEATURES, 0);
+        write_reg(DRIVER_FEATURES_SEL, 1);
+        write_reg(
+            DRIVER_FEATURES,
+            if version == 2 { VIRTIO_F_VERSION_1 } else { 0 },
+        );
+        status |= STATUS_FEATURES_OK;
+        write_reg(STATUS, status);
+        assert!(read_reg(STATUS) & STATUS_FEATURES_OK != 0);
+        // the request queue is queue 0
+        write_reg(QUEUE_SEL, 0);
+        assert!(read_reg(QUEUE_NUM_MAX) as usize >= QUEUE_SIZE);
+        write_reg(QUEUE_NUM, QUEUE_SIZE as u32);
+        let inner = self.inner.exclusive_access();
+        let desc = addr_of!(inner.queue.driver.desc) as u64;
+        let avail = addr_of!(inner.queue.driver.avail) as u64;
+        let used = addr_of!(inner.queue.device.used) as u64;
+        drop(inner);
+        if version == 1 {
+            write_reg(GUEST_PAGE_SIZE, PAGE_SIZE as u32);
+            write_reg(QUEUE_ALIGN, PAGE_SIZE as u32);
+            write_reg(QUEUE_PFN, (desc / PAGE_SIZE as u64) as u32);
+        } else {
+            write_reg(QUEUE_DESC_LOW, desc as u32);
+            write_reg(QUEUE_DESC_HIGH, (desc >> 32) as u32);
+            write_reg(QUEUE_DRIVER_LOW, avail as u32);
+            write_reg(QUEUE_DRIVER_HIGH, (avail >> 32) as u32);
+            write_reg(QUEUE_DEVICE_LOW, used as u32);
+            write_reg(QUEUE_DEVICE_HIGH, (used >> 32) as u32);
+            write_reg(QUEUE_READY, 1);
+        }
+        status |= STATUS_DRIVER_OK;
+        write_reg(STATUS, status);
+    }
+
+    /// Submit a request for block `block_id` with `data` as its data
+    /// buffer, written by the device if `write` is false, and wait for it
+    fn request(&self, block_id: usize, data: *mut u8, write: bool) {
+        let mut inner = self.inner.exclusive_access();
+        let inner = &mut **inner;
+        inner.header = BlkReqHeader {
+            req_type: if write {
+                VIRTIO_BLK_T_OUT
+            } else {
+                VIRTIO_BLK_T_IN
+            },
+            reserved: 0,
+            sector: block_id as u64,
+        };
+        inner.status = 0xff;
+        let desc = &mut inner.queue.driver.desc;
+        desc[0] = Descriptor {
+            addr: addr_of!(inner.header) as u64,
+            len: core::mem::size_of::<BlkReqHeader>() as u32,
+            flags: VIRTQ_DESC_F_NEXT,
+            next: 1,
+        };
+        desc[1] = Descriptor {
+            addr: data as u64,
+            len: BLOCK_SZ as u32,
+            flags: VIRTQ_DESC_F_NEXT | if write { 0 } else { VIRTQ_DESC_F_WRITE },
+            next: 2,
+        };
+        desc[2] = Descriptor {
+            addr: addr_of_mut!(inner.status) as u64,
+            len: 1,
+            flags: VIRTQ_DESC_F_WRITE,
+            next: 0,
+        };
+        let avail = &mut inner.queue.driver.avail;
+        avail.ring[avail.idx as usize % QUEUE_SIZE] = 0;
+        // the descriptors must be visible before the new available index
+        fence(Ordering::SeqCst);
+        avail.idx = avail.idx.wrapping_add(1);
+        fence(Ordering::SeqCst);
+        write_reg(QUEUE_NOTIFY, 0);
+        // poll for completion
+        let used_idx = addr_of!(inner.queue.device.used.idx);
+        while unsafe { read_volatile(used_idx) } == inner.last_used_idx {
+            core::hint::spin_loop();
+        }
+        fence(Ordering::SeqCst);
+        inner.last_used_idx = inner.last_used_idx.wrapping_add(1);
+        // nobody waits for the interrupt, but keep the line quiet
+        write_reg(INTERRUPT_ACK, read_reg(INTERRUPT_STATUS));
+        let status = unsafe { read_volatile(addr_of!(inner.status)) };
+        assert_eq!(status, VIRTIO_BLK_S_OK, "virtio block request failed");
+    }
+}
+
+impl BlockDevice for VirtIOBlock {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        assert_eq!(buf.len(), BLOCK_SZ);
+        self.request(block_id, buf.as_mut_ptr(), false);
+    }
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        assert_eq!(buf.len(), BLOCK_SZ);
+        self.request(block_id, buf.as_ptr() as *mut u8, true);
+    }
+}
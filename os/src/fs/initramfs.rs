@@ -0,0 +1,120 @@
+//! The initramfs: a read-only in-memory filesystem
+//!
+//! `build.rs` packs the user apps as `bin/<app>`, along with the files under
+//! `user/rootfs`, into an archive in cpio newc format embedded into the
+//! kernel. It is parsed once into a table of paths pointing into the archive,
+//! so reading a file copies straight from the kernel image.
+
+use super::{File, Stat, StatMode};
+use crate::sync::UPSafeCell;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+static INITRAMFS_IMAGE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/initramfs.cpio"));
+
+const NEWC_MAGIC: &[u8] = b"070701";
+const NEWC_HEADER_SIZE: usize = 110;
+const NEWC_TRAILER: &str = "TRAILER!!!";
+/// File type bits of the mode of an entry
+const S_IFMT: u32 = 0o170000;
+/// Type of regular files
+const S_IFREG: u32 = 0o100000;
+
+/// A file of the initramfs
+pub struct InitramfsEntry {
+    /// path without the leading `/`
+    pub path: &'static str,
+    /// content of the file
+    pub data: &'static [u8],
+}
+
+lazy_static! {
+    /// Regular files of the initramfs, in archive order
+    pub static ref INITRAMFS: Vec<InitramfsEntry> = parse(INITRAMFS_IMAGE);
+}
+
+fn align4(n: usize) -> usize {
+    (n + 3) & !3
+}
+
+/// Parse the 8 hex digit field `index` of the header at `header`
+fn header_field(header: &[u8], index: usize) -> usize {
+    let start = NEWC_MAGIC.len() + index * 8;
+    let digits = core::str::from_utf8(&header[start..start + 8]).unwrap();
+    usize::from_str_radix(digits, 16).expect("bad cpio header")
+}
+
+fn parse(image: &'static [u8]) -> Vec<InitramfsEntry> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    loop {
+        let header = &image[pos..pos + NEWC_HEADER_SIZE];
+        assert_eq!(&header[..NEWC_MAGIC.len()], NEWC_MAGIC, "bad cpio magic");
+        let mode = header_field(header, 1) as u32;
+        let file_size = header_field(header, 6);
+        let name_size = header_field(header, 11);
+        let name_start = pos + NEWC_HEADER_SIZE;
+        // the name ends with a NUL
+        let path = core::str::from_utf8(&image[name_start..name_start + name_size - 1]).unwrap();
+        let data_start = align4(name_start + name_size);
+        if path == NEWC_TRAILER {
+            break;
+        }
+        if mode & S_IFMT == S_IFREG {
+            entries.push(InitramfsEntry {
+                path: path.trim_start_matches("./"),
+                data: &image[data_start..data_start + file_size],
+            });
+        }
+        pos = align4(data_start + file_size);
+    }
+    entries
+}
+
+/// An open file of the initramfs, which is read only
+pub struct InitramfsFile {
+    /// index of the file in [`INITRAMFS`]
+    index: usize,
+    offset: UPSafeCell<usize>,
+}
+
+/// Open the file at `path` of the initramfs
+pub fn open_initramfs_file(path: &str) -> Option<Arc<InitramfsFile>> {
+    let path = path.trim_start_matches('/');
+    INITRAMFS
+        .iter()
+        .position(|entry| entry.path == path)
+        .map(|index| {
+            Arc::new(InitramfsFile {
+                index,
+                offset: unsafe { UPSafeCell::new(0) },
+            })
+        })
+}
+
+impl File for InitramfsFile {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn read(&self, buf: &mut [u8]) -> usize {
+        let data = INITRAMFS[self.index].data;
+        let mut offset = self.offset.exclusive_access();
+        let len = buf.len().min(data.len() - *offset);
+        buf[..len].copy_from_slice(&data[*offset..*offset + len]);
+        *offset += len;
+        len
+    }
+    fn write(&self, _buf: &[u8]) -> usize {
+        panic!("Cannot write to the initramfs!");
+    }
+    fn stat(&self) -> Option<Stat> {
+        // keep inode numbers apart from those of easy-fs with the device
+        let mut stat = Stat::new(self.index as u64 + 1, StatMode::FILE, 1);
+        stat.dev = 1;
+        Some(stat)
+    }
+}
@@ -0,0 +1,141 @@
+//! `Arc<Inode>` -> `OSInodeInner`: In order to open files concurrently
+//! we need to wrap `Inode` into `Arc`,but `Mutex` in `Inode` prevents
+//! file systems from being accessed simultaneously
+//!
+//! `UPSafeCell<OSInodeInner>` -> `OSInode`: for static `ROOT_INODE`,we
+//! need to wrap `OSInodeInner` into `UPSafeCell`
+use super::{File, Stat, StatMode, INITRAMFS};
+use crate::drivers::BLOCK_DEVICE;
+use crate::sync::UPSafeCell;
+use alloc::sync::Arc;
+use bitflags::*;
+use easy_fs::{EasyFileSystem, Inode};
+use lazy_static::*;
+
+/// A wrapper around a filesystem inode
+/// to implement File trait atop
+pub struct OSInode {
+    readable: bool,
+    writable: bool,
+    inner: UPSafeCell<OSInodeInner>,
+}
+/// The OS inode inner in 'UPSafeCell'
+pub struct OSInodeInner {
+    offset: usize,
+    inode: Arc<Inode>,
+}
+
+impl OSInode {
+    /// Construct an OS inode from a inode
+    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>) -> Self {
+        Self {
+            readable,
+            writable,
+            inner: unsafe { UPSafeCell::new(OSInodeInner { offset: 0, inode }) },
+        }
+    }
+}
+
+lazy_static! {
+    /// The root directory of the filesystem on the block device
+    pub static ref ROOT_INODE: Arc<Inode> = {
+        let efs = EasyFileSystem::open(BLOCK_DEVICE.clone());
+        Arc::new(EasyFileSystem::root_inode(&efs))
+    };
+}
+
+/// List all apps in the initramfs
+pub fn list_apps() {
+    println!("/**** APPS ****");
+    for entry in INITRAMFS.iter() {
+        if let Some(app) = entry.path.strip_prefix("bin/") {
+            println!("{}", app);
+        }
+    }
+    println!("**************/");
+}
+
+bitflags! {
+    ///Open file flags, read only if neither `WRONLY` nor `RDWR` is given
+    pub struct OpenFlags: u32 {
+        ///Write only
+        const WRONLY = 1 << 0;
+        ///Read & Write
+        const RDWR = 1 << 1;
+        ///Allow create
+        const CREATE = 1 << 9;
+        ///Clear file and return an empty one
+        const TRUNC = 1 << 10;
+    }
+}
+
+impl OpenFlags {
+    /// Whether the file may be changed when it is opened
+    pub fn modifies(&self) -> bool {
+        self.intersects(Self::WRONLY | Self::RDWR | Self::CREATE | Self::TRUNC)
+    }
+    /// Get the current read write permission on an inode
+    /// does not check validity for simplicity
+    /// returns (readable, writable)
+    pub fn read_write(&self) -> (bool, bool) {
+        if self.contains(Self::WRONLY) {
+            (false, true)
+        } else if self.contains(Self::RDWR) {
+            (true, true)
+        } else {
+            (true, false)
+        }
+    }
+}
+
+/// Open a file in the root directory, creating it first if it does not
+/// exist and `CREATE` is given, which fails if the disk is full
+pub fn open_file(name: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
+    let (readable, writable) = flags.read_write();
+    let inode = match ROOT_INODE.find(name) {
+        Some(inode) => {
+            if flags.contains(OpenFlags::TRUNC) {
+                inode.clear();
+            }
+            inode
+        }
+        None if flags.contains(OpenFlags::CREATE) => ROOT_INODE.create(name)?,
+        None => return None,
+    };
+    Some(Arc::new(OSInode::new(readable, writable, inode)))
+}
+
+impl File for OSInode {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    fn read(&self, buf: &mut [u8]) -> usize {
+        let mut inner = self.inner.exclusive_access();
+        let read_size = inner.inode.read_at(inner.offset, buf);
+        inner.offset += read_size;
+        read_size
+    }
+    fn write(&self, buf: &[u8]) -> usize {
+        let mut inner = self.inner.exclusive_access();
+        // short if the disk got full
+        let write_size = inner.inode.write_at(inner.offset, buf);
+        inner.offset += write_size;
+        write_size
+    }
+    fn stat(&self) -> Option<Stat> {
+        let inner = self.inner.exclusive_access();
+        let mode = if inner.inode.is_dir() {
+            StatMode::DIR
+        } else {
+            StatMode::FILE
+        };
+        Some(Stat::new(
+            inner.inode.inode_id() as u64,
+            mode,
+            inner.inode.nlink(),
+        ))
+    }
+}
@@ -0,0 +1,78 @@
+//! File system in os
+//!
+//! Every open file of a process is a [`File`], found through the file
+//! descriptor table of the process. Standard input and output are files as
+//! well, and so are both ends of a pipe and the regular files of easy-fs,
+//! opened through [`open_file`].
+//!
+//! Absolute paths refer to the read-only initramfs embedded into the kernel,
+//! which holds the apps under `/bin`. Other paths are names in the flat root
+//! directory of easy-fs.
+
+mod initramfs;
+mod inode;
+mod pipe;
+mod stdio;
+
+use bitflags::*;
+
+/// File trait
+pub trait File: Send + Sync {
+    /// If readable
+    fn readable(&self) -> bool;
+    /// If writable
+    fn writable(&self) -> bool;
+    /// Read file to `buf`, return the number of bytes read
+    fn read(&self, buf: &mut [u8]) -> usize;
+    /// Write `buf` to file, return the number of bytes written
+    fn write(&self, buf: &[u8]) -> usize;
+    /// Status of the file, if it is backed by an inode
+    fn stat(&self) -> Option<Stat> {
+        None
+    }
+}
+
+/// The stat of a inode
+#[repr(C)]
+#[derive(Debug)]
+pub struct Stat {
+    /// ID of device containing file
+    pub dev: u64,
+    /// inode number
+    pub ino: u64,
+    /// file type and mode
+    pub mode: StatMode,
+    /// number of hard links
+    pub nlink: u32,
+    /// unused pad
+    pad: [u64; 7],
+}
+
+impl Stat {
+    /// Create the stat of inode `ino` on the only device
+    pub fn new(ino: u64, mode: StatMode, nlink: u32) -> Self {
+        Self {
+            dev: 0,
+            ino,
+            mode,
+            nlink,
+            pad: [0; 7],
+        }
+    }
+}
+
+bitflags! {
+    /// The mode of a inode
+    /// whether a directory or a file
+    pub struct StatMode: u32 {
+        /// directory
+        const DIR   = 0o040000;
+        /// ordinary regular file
+        const FILE  = 0o100000;
+    }
+}
+
+pub use initramfs::{open_initramfs_file, INITRAMFS};
+pub use inode::{list_apps, open_file, OpenFlags, ROOT_INODE};
+pub use pipe::make_pipe;
+pub use stdio::{Stdin, Stdout};
@@ -0,0 +1,201 @@
+//! Pipes
+//!
+//! Both ends of a pipe share a ring buffer. A reader finding the buffer
+//! empty and a writer finding it full are blocked until the other side
+//! makes progress, or until every end on the other side is closed.
+
+use super::File;
+use crate::sync::UPSafeCell;
+use crate::task::{block_current_and_run_next, current_task_id, wakeup_task};
+use alloc::collections::VecDeque;
+use alloc::sync::{Arc, Weak};
+
+const RING_BUFFER_SIZE: usize = 32;
+
+/// One end of a pipe
+pub struct Pipe {
+    readable: bool,
+    writable: bool,
+    buffer: Arc<UPSafeCell<PipeRingBuffer>>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum RingBufferStatus {
+    Full,
+    Empty,
+    Normal,
+}
+
+/// Ring buffer shared by the ends of a pipe
+pub struct PipeRingBuffer {
+    arr: [u8; RING_BUFFER_SIZE],
+    head: usize,
+    tail: usize,
+    status: RingBufferStatus,
+    /// set once, kept alive by every file descriptor of the read end
+    read_end: Option<Weak<Pipe>>,
+    /// set once, kept alive by every file descriptor of the write end
+    write_end: Option<Weak<Pipe>>,
+    /// tasks waiting for something to read
+    read_waiters: VecDeque<usize>,
+    /// tasks waiting for room to write
+    write_waiters: VecDeque<usize>,
+}
+
+impl PipeRingBuffer {
+    fn new() -> Self {
+        Self {
+            arr: [0; RING_BUFFER_SIZE],
+            head: 0,
+            tail: 0,
+            status: RingBufferStatus::Empty,
+            read_end: None,
+            write_end: None,
+            read_waiters: VecDeque::new(),
+            write_waiters: VecDeque::new(),
+        }
+    }
+    fn write_byte(&mut self, byte: u8) {
+        self.status = RingBufferStatus::Normal;
+        self.arr[self.tail] = byte;
+        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
+        if self.tail == self.head {
+            self.status = RingBufferStatus::Full;
+        }
+    }
+    fn read_byte(&mut self) -> u8 {
+        self.status = RingBufferStatus::Normal;
+        let c = self.arr[self.head];
+        self.head = (self.head + 1) % RING_BUFFER_SIZE;
+        if self.head == self.tail {
+            self.status = RingBufferStatus::Empty;
+        }
+        c
+    }
+    fn available_read(&self) -> usize {
+        if self.status == RingBufferStatus::Empty {
+            0
+        } else if self.tail > self.head {
+            self.tail - self.head
+        } else {
+            self.tail + RING_BUFFER_SIZE - self.head
+        }
+    }
+    fn available_write(&self) -> usize {
+        if self.status == RingBufferStatus::Full {
+            0
+        } else {
+            RING_BUFFER_SIZE - self.available_read()
+        }
+    }
+    fn all_read_ends_closed(&self) -> bool {
+        self.read_end.as_ref().unwrap().upgrade().is_none()
+    }
+    fn all_write_ends_closed(&self) -> bool {
+        self.write_end.as_ref().unwrap().upgrade().is_none()
+    }
+}
+
+/// Wake up every task in `waiters`
+fn wakeup_all(waiters: &mut VecDeque<usize>) {
+    while let Some(task) = waiters.pop_front() {
+        wakeup_task(task);
+    }
+}
+
+/// Return (read_end, write_end) of a new pipe
+pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
+    let buffer = Arc::new(unsafe { UPSafeCell::new(PipeRingBuffer::new()) });
+    let read_end = Arc::new(Pipe {
+        readable: true,
+        writable: false,
+        buffer: buffer.clone(),
+    });
+    let write_end = Arc::new(Pipe {
+        readable: false,
+        writable: true,
+        buffer: buffer.clone(),
+    });
+    let mut inner = buffer.exclusive_access();
+    inner.read_end = Some(Arc::downgrade(&read_end));
+    inner.write_end = Some(Arc::downgrade(&write_end));
+    drop(inner);
+    (read_end, write_end)
+}
+
+impl File for Pipe {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    /// Read what is available, blocking while the pipe is empty.
+    ///
+    /// Return 0 once the pipe is empty and all write ends are closed.
+    fn read(&self, buf: &mut [u8]) -> usize {
+        assert!(self.readable());
+        if buf.is_empty() {
+            return 0;
+        }
+        loop {
+            let mut ring_buffer = self.buffer.exclusive_access();
+            let loop_read = ring_buffer.available_read();
+            if loop_read == 0 {
+                if ring_buffer.all_write_ends_closed() {
+                    return 0;
+                }
+                ring_buffer.read_waiters.push_back(current_task_id());
+                drop(ring_buffer);
+                block_current_and_run_next();
+                continue;
+            }
+            let read_size = loop_read.min(buf.len());
+            for byte in buf[..read_size].iter_mut() {
+                *byte = ring_buffer.read_byte();
+            }
+            wakeup_all(&mut ring_buffer.write_waiters);
+            return read_size;
+        }
+    }
+    /// Write the whole of `buf`, blocking while the pipe is full.
+    ///
+    /// Stop early once all read ends are closed, as nobody will ever read.
+    fn write(&self, buf: &[u8]) -> usize {
+        assert!(self.writable());
+        let mut already_write = 0usize;
+        while already_write < buf.len() {
+            let mut ring_buffer = self.buffer.exclusive_access();
+            if ring_buffer.all_read_ends_closed() {
+                break;
+            }
+            let loop_write = ring_buffer.available_write();
+            if loop_write == 0 {
+                ring_buffer.write_waiters.push_back(current_task_id());
+                drop(ring_buffer);
+                block_current_and_run_next();
+                continue;
+            }
+            let write_size = loop_write.min(buf.len() - already_write);
+            for byte in buf[already_write..already_write + write_size].iter() {
+                ring_buffer.write_byte(*byte);
+            }
+            already_write += write_size;
+            wakeup_all(&mut ring_buffer.read_waiters);
+        }
+        already_write
+    }
+}
+
+impl Drop for Pipe {
+    /// When the last descriptor of an end goes away, tasks blocked waiting
+    /// for the other side have to see it closed.
+    fn drop(&mut self) {
+        let mut ring_buffer = self.buffer.exclusive_access();
+        if self.readable {
+            wakeup_all(&mut ring_buffer.write_waiters);
+        } else {
+            wakeup_all(&mut ring_buffer.read_waiters);
+        }
+    }
+}
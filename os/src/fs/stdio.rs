@@ -0,0 +1,55 @@
+//! Stdin & Stdout
+use super::File;
+use crate::console::put_bytes;
+use crate::sbi::console_getchar;
+use crate::task::suspend_current_and_run_next;
+
+/// stdin file for getting chars from console
+pub struct Stdin;
+
+/// stdout file for putting chars to console, also used as stderr
+pub struct Stdout;
+
+impl File for Stdin {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    /// Read a single char, waiting until one is typed
+    fn read(&self, buf: &mut [u8]) -> usize {
+        if buf.is_empty() {
+            return 0;
+        }
+        let c = loop {
+            match console_getchar() {
+                // nothing typed yet
+                usize::MAX => suspend_current_and_run_next(),
+                c => break c,
+            }
+        };
+        buf[0] = c as u8;
+        1
+    }
+    fn write(&self, _buf: &[u8]) -> usize {
+        panic!("Cannot write to stdin!");
+    }
+}
+
+impl File for Stdout {
+    fn readable(&self) -> bool {
+        false
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, _buf: &mut [u8]) -> usize {
+        panic!("Cannot read from stdout!");
+    }
+    /// Write the bytes as they are, as apps may write anything
+    fn write(&self, buf: &[u8]) -> usize {
+        put_bytes(buf);
+        buf.len()
+    }
+}
@@ -0,0 +1,70 @@
+//! In-kernel test framework
+//!
+//! `cargo test` (or `make test`) builds the kernel with the `#[test_case]`
+//! functions of every module, and `rust_main` calls the generated
+//! `test_main` once the kernel is initialized, before any task runs. Tests
+//! run one after another on the boot stack; a failing test panics, which
+//! stops the run.
+//!
+//! Results are printed one per line with a `[ktest]` prefix:
+//!
+//! ```text
+//! [ktest] running 2 tests
+//! [ktest] test os::timer::tests::time_goes_forward ... ok
+//! [ktest] test os::loader::tests::apps_are_loaded ... FAILED
+//! [ktest] result: FAILED. 1 passed; 1 failed
+//! ```
+//!
+//! and the kernel shuts down as a success only if every test passed.
+
+use crate::sbi::shutdown;
+use crate::sync::UPSafeCell;
+
+/// A test function, named after its path
+pub trait Testable {
+    /// Run the test, printing its name and result
+    fn run(&self);
+}
+
+impl<T: Fn()> Testable for T {
+    fn run(&self) {
+        let name = core::any::type_name::<T>();
+        *CURRENT_TEST.exclusive_access() = Some(name);
+        print!("[ktest] test {} ... ", name);
+        self();
+        println!("ok");
+        *CURRENT_TEST.exclusive_access() = None;
+        *PASSED.exclusive_access() += 1;
+    }
+}
+
+/// Name of the running test
+static CURRENT_TEST: UPSafeCell<Option<&'static str>> = unsafe { UPSafeCell::new(None) };
+/// Number of tests passed so far
+static PASSED: UPSafeCell<usize> = unsafe { UPSafeCell::new(0) };
+
+/// Run every test and shut down
+pub fn test_runner(tests: &[&dyn Testable]) {
+    println!("[ktest] running {} tests", tests.len());
+    for test in tests {
+        test.run();
+    }
+    println!(
+        "[ktest] result: ok. {} passed; 0 failed",
+        PASSED.exclusive_access()
+    );
+    shutdown(false)
+}
+
+/// Report the running test, if any, as failed. Called by the panic handler,
+/// which then shuts down as a failure.
+pub fn fail_current_test() {
+    if let Some(name) = *CURRENT_TEST.exclusive_access() {
+        println!("FAILED");
+        println!(
+            "[ktest] result: FAILED. {} passed; 1 failed ({})",
+            PASSED.exclusive_access(),
+            name
+        );
+    }
+}
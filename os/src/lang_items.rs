@@ -1,11 +1,16 @@
 //! The panic handler
 
+use crate::backtrace::print_backtrace;
 use crate::sbi::shutdown;
+use crate::task::running_task_id;
+use crate::trap::{last_trap_context, TrapContext};
 use core::panic::PanicInfo;
 use log::*;
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    #[cfg(test)]
+    crate::ktest::fail_current_test();
     if let Some(location) = info.location() {
         error!(
             "[kernel] Panicked at {}:{} {}",
@@ -16,5 +21,40 @@ fn panic(info: &PanicInfo) -> ! {
     } else {
         error!("[kernel] Panicked: {}", info.message().unwrap());
     }
+    print_backtrace();
+    match running_task_id() {
+        Some(task_id) => {
+            println!("Current task: {}", task_id);
+        }
+        None => {
+            println!("Current task: none");
+        }
+    }
+    if let Some((task_id, cx)) = last_trap_context() {
+        println!("Last trap from task {}:", task_id);
+        print_trap_context(cx);
+    }
     shutdown(true)
 }
+
+/// Print the registers saved in `cx`
+fn print_trap_context(cx: &TrapContext) {
+    println!(
+        "sepc    {:#018x}  sstatus {:#018x}",
+        cx.sepc,
+        cx.sstatus.bits()
+    );
+    for (row, regs) in cx.x.chunks(4).enumerate() {
+        println!(
+            "x{:<2}     {:#018x}  x{:<2}     {:#018x}  x{:<2}     {:#018x}  x{:<2}     {:#018x}",
+            row * 4,
+            regs[0],
+            row * 4 + 1,
+            regs[1],
+            row * 4 + 2,
+            regs[2],
+            row * 4 + 3,
+            regs[3]
+        );
+    }
+}
@@ -1,13 +1,23 @@
 //! Loading user applications into memory
 //!
-//! For chapter 3, user applications are simply part of the data included in the
-//! kernel binary, so we only need to copy them to the space allocated for each
-//! app to load them. We also allocate fixed spaces for each task's
-//! [`KernelStack`] and [`UserStack`].
+//! User applications are ELF files under `/bin` of the initramfs.
+//! Each one is linked to run in a fixed slot of memory, so we only need to copy
+//! its loadable segments there to load it. The rest of the slot, after the
+//! `.bss` of the app, is left for its heap. We also allocate fixed spaces for
+//! each task's [`KernelStack`] and [`UserStack`].
+//!
+//! Threads created at runtime get their stacks from the kernel heap instead,
+//! see [`ThreadStack`].
 
 use crate::config::*;
-use crate::trap::TrapContext;
+use crate::fs::INITRAMFS;
+use crate::sync::UPSafeCell;
+use crate::trap::{forget_trap_context_on, TrapContext};
+use alloc::{vec, vec::Vec};
 use core::arch::asm;
+use core::ops::Range;
+use lazy_static::*;
+use log::*;
 
 #[repr(align(4096))]
 #[derive(Copy, Clone)]
@@ -42,47 +52,186 @@ impl KernelStack {
     }
 }
 
+impl Drop for ThreadStack {
+    fn drop(&mut self) {
+        // the panic handler must not read a context freed with the stack
+        forget_trap_context_on(&self.kernel);
+    }
+}
+
 impl UserStack {
     fn get_sp(&self) -> usize {
         self.data.as_ptr() as usize + USER_STACK_SIZE
     }
 }
 
+/// Kernel and user stacks of a thread created at runtime
+pub struct ThreadStack {
+    kernel: Vec<u8>,
+    user: Vec<u8>,
+}
+
+impl ThreadStack {
+    /// Allocate zeroed stacks on the kernel heap
+    pub fn new() -> Self {
+        Self {
+            kernel: vec![0; KERNEL_STACK_SIZE],
+            user: vec![0; USER_STACK_SIZE],
+        }
+    }
+    /// Top of the user stack, aligned as the calling convention requires
+    pub fn user_sp(&self) -> usize {
+        (self.user.as_ptr() as usize + USER_STACK_SIZE) & !0xf
+    }
+    /// Save `trap_cx` on top of the kernel stack and return its address
+    pub fn push_context(&self, trap_cx: TrapContext) -> usize {
+        let kernel_sp = (self.kernel.as_ptr() as usize + KERNEL_STACK_SIZE) & !0xf;
+        let trap_cx_ptr = (kernel_sp - core::mem::size_of::<TrapContext>()) as *mut TrapContext;
+        unsafe {
+            *trap_cx_ptr = trap_cx;
+        }
+        trap_cx_ptr as usize
+    }
+}
+
+impl Drop for ThreadStack {
+    fn drop(&mut self) {
+        // the panic handler must not read a context freed with the stack
+        forget_trap_context_on(&self.kernel);
+    }
+}
+
 /// Get base address of app i.
 fn get_base_i(app_id: usize) -> usize {
     APP_BASE_ADDRESS + app_id * APP_SIZE_LIMIT
 }
 
+/// A loaded app
+#[derive(Clone, Copy)]
+struct App {
+    entry: usize,
+    /// file name under `/bin`
+    name: &'static str,
+    /// end of its loadable segments, `.bss` included
+    end: usize,
+}
+
+lazy_static! {
+    /// The loaded apps, indexed by app id
+    static ref APPS: UPSafeCell<Vec<App>> = unsafe { UPSafeCell::new(Vec::new()) };
+}
+
 /// Get the total number of applications.
 pub fn get_num_app() -> usize {
-    extern "C" {
-        fn _num_app();
+    APPS.exclusive_access().len()
+}
+
+/// Get the name of app i, its file name under `/bin`.
+pub fn get_app_name(app_id: usize) -> &'static str {
+    APPS.exclusive_access()[app_id].name
+}
+
+/// Get the memory left for the heap of app i, from the end of its `.bss`
+/// to the end of its slot.
+pub fn get_app_heap(app_id: usize) -> Range<usize> {
+    // keep the heap aligned like the stack
+    let start = (APPS.exclusive_access()[app_id].end + 15) & !15;
+    start..get_base_i(app_id) + APP_SIZE_LIMIT
+}
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const PT_LOAD: u32 = 1;
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> usize {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap()) as usize
+}
+
+/// Copy the loadable segments of the ELF file `elf` into the slot of the app
+/// it is linked for, and return the app id with the loaded app `name`.
+/// Return `None` if it is not an app.
+fn load_elf(elf: &[u8], name: &'static str) -> Option<(usize, App)> {
+    if elf.len() < 64 || &elf[..4] != ELF_MAGIC {
+        return None;
+    }
+    let entry = read_u64(elf, 24);
+    let ph_offset = read_u64(elf, 32);
+    let ph_entry_size = read_u16(elf, 54) as usize;
+    let ph_count = read_u16(elf, 56) as usize;
+    // build.py links app i at the base of slot i
+    let app_id = entry.checked_sub(APP_BASE_ADDRESS)? / APP_SIZE_LIMIT;
+    if app_id >= MAX_APP_NUM {
+        return None;
     }
-    unsafe { (_num_app as usize as *const usize).read_volatile() }
+    let base_i = get_base_i(app_id);
+    let mut end = base_i;
+    // clear region
+    (base_i..base_i + APP_SIZE_LIMIT)
+        .for_each(|addr| unsafe { (addr as *mut u8).write_volatile(0) });
+    for i in 0..ph_count {
+        let ph = &elf[ph_offset + i * ph_entry_size..];
+        if read_u32(ph, 0) != PT_LOAD {
+            continue;
+        }
+        let offset = read_u64(ph, 8);
+        let vaddr = read_u64(ph, 16);
+        let file_size = read_u64(ph, 32);
+        let mem_size = read_u64(ph, 40);
+        assert!(
+            vaddr >= base_i && vaddr + mem_size <= base_i + APP_SIZE_LIMIT,
+            "app {} does not fit in its slot",
+            app_id
+        );
+        // the part beyond the file size is bss, which has been cleared
+        let dst = unsafe { core::slice::from_raw_parts_mut(vaddr as *mut u8, file_size) };
+        dst.copy_from_slice(&elf[offset..offset + file_size]);
+        end = end.max(vaddr + mem_size);
+    }
+    Some((app_id, App { entry, name, end }))
 }
 
-/// Load nth user app at
+/// Load the app ELFs found under `/bin` of the initramfs. The nth user app
+/// is loaded at
 /// [APP_BASE_ADDRESS + n * APP_SIZE_LIMIT, APP_BASE_ADDRESS + (n+1) * APP_SIZE_LIMIT).
 pub fn load_apps() {
     extern "C" {
-        fn _num_app();
+        fn ekernel();
     }
-    let num_app_ptr = _num_app as usize as *const usize;
-    let num_app = get_num_app();
-    let app_start = unsafe { core::slice::from_raw_parts(num_app_ptr.add(1), num_app + 1) };
-    // load apps
-    for i in 0..num_app {
-        let base_i = get_base_i(i);
-        // clear region
-        (base_i..base_i + APP_SIZE_LIMIT)
-            .for_each(|addr| unsafe { (addr as *mut u8).write_volatile(0) });
-        // load app from data section to memory
-        let src = unsafe {
-            core::slice::from_raw_parts(app_start[i] as *const u8, app_start[i + 1] - app_start[i])
-        };
-        let dst = unsafe { core::slice::from_raw_parts_mut(base_i as *mut u8, src.len()) };
-        dst.copy_from_slice(src);
+    assert!(
+        ekernel as usize <= APP_BASE_ADDRESS,
+        "the kernel overlaps the apps"
+    );
+    let mut apps = APPS.exclusive_access();
+    for entry in INITRAMFS.iter() {
+        if !entry.path.starts_with("bin/") {
+            continue;
+        }
+        let name = entry.path.trim_start_matches("bin/");
+        if let Some((app_id, app)) = load_elf(entry.data, name) {
+            debug!("[kernel] load app {} from /{}", app_id, entry.path);
+            if apps.len() <= app_id {
+                let missing = App {
+                    entry: 0,
+                    name: "",
+                    end: 0,
+                };
+                apps.resize(app_id + 1, missing);
+            }
+            apps[app_id] = app;
+        }
     }
+    assert!(!apps.is_empty(), "no app found in the initramfs");
+    assert!(
+        apps.iter().all(|app| app.entry != 0),
+        "some app is missing from the initramfs"
+    );
     // Memory fence about fetching the instruction memory
     // It is guaranteed that a subsequent instruction fetch must
     // observes all previous writes to the instruction memory.
@@ -97,7 +246,22 @@ pub fn load_apps() {
 /// get app info with entry and sp and save `TrapContext` in kernel stack
 pub fn init_app_cx(app_id: usize) -> usize {
     KERNEL_STACK[app_id].push_context(TrapContext::app_init_context(
-        get_base_i(app_id),
+        APPS.exclusive_access()[app_id].entry,
         USER_STACK[app_id].get_sp(),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn apps_are_loaded_in_their_slots() {
+        assert!(get_num_app() > 0);
+        for (app_id, app) in APPS.exclusive_access().iter().enumerate() {
+            assert_eq!(app.entry, get_base_i(app_id));
+            assert!(!app.name.is_empty());
+            assert!(app.end > app.entry && app.end <= get_base_i(app_id + 1));
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Per-module log filtering
+//!
+//! A filter is written as comma-separated directives, like
+//! `info,task=trace,trap=warn`. A bare level sets the level of every module
+//! no directive names, and `module=level` sets the level of a module path
+//! below the crate root together with its submodules. When several
+//! directives match a record, the longest module path wins.
+//!
+//! The filter is stored in fixed arrays, as it is parsed before the heap is
+//! initialized.
+
+use core::str::FromStr;
+use log::LevelFilter;
+
+/// Maximum number of `module=level` directives
+const MAX_DIRECTIVES: usize = 8;
+/// Maximum length of the module path of a directive
+const MODULE_MAX: usize = 32;
+
+#[derive(Clone, Copy)]
+struct Directive {
+    module: [u8; MODULE_MAX],
+    len: usize,
+    level: LevelFilter,
+}
+
+impl Directive {
+    const EMPTY: Self = Self {
+        module: [0; MODULE_MAX],
+        len: 0,
+        level: LevelFilter::Off,
+    };
+
+    fn module(&self) -> &str {
+        core::str::from_utf8(&self.module[..self.len]).unwrap()
+    }
+
+    /// Whether `path`, a module path without the crate name, is the module
+    /// of this directive or one of its submodules
+    fn matches(&self, path: &str) -> bool {
+        let module = self.module();
+        path.strip_prefix(module)
+            .map_or(false, |rest| rest.is_empty() || rest.starts_with("::"))
+    }
+}
+
+/// Levels of log records to keep, by module
+#[derive(Clone, Copy)]
+pub struct LogFilter {
+    default: LevelFilter,
+    directives: [Directive; MAX_DIRECTIVES],
+    count: usize,
+}
+
+impl LogFilter {
+    /// A filter keeping records up to `level` in every module
+    pub const fn new(level: LevelFilter) -> Self {
+        Self {
+            default: level,
+            directives: [Directive::EMPTY; MAX_DIRECTIVES],
+            count: 0,
+        }
+    }
+
+    /// Parse a filter, with modules no directive names kept up to `Info`
+    /// unless the spec has a bare level. Return `None` for unknown levels,
+    /// and for too many or too long module paths.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut filter = Self::new(LevelFilter::Info);
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                None => filter.default = LevelFilter::from_str(directive).ok()?,
+                Some((module, level)) => {
+                    let module = module.trim().trim_start_matches("os::");
+                    if filter.count == MAX_DIRECTIVES || module.len() > MODULE_MAX {
+                        return None;
+                    }
+                    let new = &mut filter.directives[filter.count];
+                    new.module[..module.len()].copy_from_slice(module.as_bytes());
+                    new.len = module.len();
+                    new.level = LevelFilter::from_str(level.trim()).ok()?;
+                    filter.count += 1;
+                }
+            }
+        }
+        Some(filter)
+    }
+
+    /// The most verbose level kept for records of the module `target`
+    pub fn level(&self, target: &str) -> LevelFilter {
+        // targets are module paths starting with the crate name
+        let path = target.split_once("::").map_or("", |(_, path)| path);
+        self.directives[..self.count]
+            .iter()
+            .filter(|directive| directive.matches(path))
+            .max_by_key(|directive| directive.len)
+            .map_or(self.default, |directive| directive.level)
+    }
+
+    /// The most verbose level kept in any module
+    pub fn max_level(&self) -> LevelFilter {
+        self.directives[..self.count]
+            .iter()
+            .map(|directive| directive.level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn longest_module_wins() {
+        let filter = LogFilter::parse("warn,task=trace,task::switch=off").unwrap();
+        assert!(filter.level("os") == LevelFilter::Warn);
+        assert!(filter.level("os::trap") == LevelFilter::Warn);
+        assert!(filter.level("os::task") == LevelFilter::Trace);
+        assert!(filter.level("os::task::task") == LevelFilter::Trace);
+        assert!(filter.level("os::task::switch") == LevelFilter::Off);
+        assert!(filter.level("os::tasks") == LevelFilter::Warn);
+        assert!(filter.max_level() == LevelFilter::Trace);
+    }
+
+    #[test_case]
+    fn bad_filters_are_rejected() {
+        assert!(LogFilter::parse("task=loud").is_none());
+        assert!(LogFilter::parse("verbose").is_none());
+    }
+}
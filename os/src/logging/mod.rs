@@ -0,0 +1,128 @@
+//! 本模块利用 log crate 为你提供了日志功能，使用方式见 main.rs.
+//!
+//! Log lines start with the time since boot in microseconds, the level and
+//! the id of the running task. Console lines are colored by level unless the
+//! `LOG_COLOR` build variable is `0`, `off` or `never`, which makes the
+//! output plain text fit for diffing.
+//!
+//! Records pass through a per-module [`LogFilter`], set from the `LOG`
+//! environment variable at build time (like `info,task=trace,trap=warn`) and
+//! replaced at runtime by `sys_set_log_filter`. Every record it lets through
+//! is kept in the kernel log ring buffer [`LOG_RING`] with its timestamp,
+//! level and task id, where `sys_syslog` reads it. Records at or above the
+//! console level are echoed to the console as well.
+
+mod filter;
+mod ring;
+
+pub use filter::LogFilter;
+pub use ring::LOG_BUF_SIZE;
+
+use crate::sync::UPSafeCell;
+use crate::task::running_task_id;
+use crate::timer::get_time_us;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use log::{self, Level, LevelFilter, Log, Metadata, Record};
+use ring::{LineBuffer, LogRing};
+
+/// The kernel log
+pub static LOG_RING: UPSafeCell<LogRing> = unsafe { UPSafeCell::new(LogRing::new()) };
+
+/// Levels of records kept, by module
+static LOG_FILTER: UPSafeCell<LogFilter> =
+    unsafe { UPSafeCell::new(LogFilter::new(LevelFilter::Info)) };
+
+/// Most verbose level echoed to the console, as a `LevelFilter`
+static CONSOLE_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Trace as usize);
+
+/// Replace the filter of records kept
+pub fn set_log_filter(filter: LogFilter) {
+    log::set_max_level(filter.max_level());
+    *LOG_FILTER.exclusive_access() = filter;
+}
+
+/// Set the most verbose level echoed to the console
+pub fn set_console_level(level: LevelFilter) {
+    CONSOLE_LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+/// Whether console lines are colored with ANSI escapes, unless the
+/// `LOG_COLOR` build variable is `0`, `off` or `never`
+fn color() -> bool {
+    !matches!(option_env!("LOG_COLOR"), Some("0" | "off" | "never"))
+}
+
+/// The start of a log line, like `[    1.234567]  INFO [3]`, with the time
+/// since boot and the id of the running task, or `-` before tasks run
+struct Prefix {
+    time_us: usize,
+    level: Level,
+    task_id: Option<usize>,
+}
+
+impl fmt::Display for Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{:>5}.{:06}] {:>5} ",
+            self.time_us / 1_000_000,
+            self.time_us % 1_000_000,
+            self.level
+        )?;
+        match self.task_id {
+            Some(task_id) => write!(f, "[{}]", task_id),
+            None => write!(f, "[-]"),
+        }
+    }
+}
+
+struct SimpleLogger;
+
+impl Log for SimpleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= LOG_FILTER.exclusive_access().level(metadata.target())
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let prefix = Prefix {
+            time_us: get_time_us(),
+            level: record.level(),
+            task_id: running_task_id(),
+        };
+        let mut line = LineBuffer::new();
+        let _ = write!(line, "{} {}", prefix, record.args());
+        LOG_RING.exclusive_access().push(line.finish());
+        if record.level() as usize > CONSOLE_LEVEL.load(Ordering::Relaxed) {
+            return;
+        }
+        if !color() {
+            println!("{} {}", prefix, record.args());
+            return;
+        }
+        let color = match record.level() {
+            Level::Error => 31, // Red
+            Level::Warn => 93,  // BrightYellow
+            Level::Info => 34,  // Blue
+            Level::Debug => 32, // Green
+            Level::Trace => 90, // BrightBlack
+        };
+        println!("\u{1B}[{}m{} {}\u{1B}[0m", color, prefix, record.args());
+    }
+    fn flush(&self) {}
+}
+
+pub fn init() {
+    static LOGGER: SimpleLogger = SimpleLogger;
+    log::set_logger(&LOGGER).unwrap();
+    let spec = option_env!("LOG").unwrap_or("info");
+    match LogFilter::parse(spec) {
+        Some(filter) => set_log_filter(filter),
+        None => {
+            set_log_filter(LogFilter::new(LevelFilter::Info));
+            log::warn!("[kernel] Invalid LOG filter {:?}, using info", spec);
+        }
+    }
+}
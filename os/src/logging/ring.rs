@@ -0,0 +1,146 @@
+//! The kernel log ring buffer
+//!
+//! Records are kept as text lines in a fixed-size byte ring. When a new
+//! record does not fit, the oldest whole records are dropped to make room,
+//! so the buffer always starts at the beginning of a record.
+
+use core::fmt::{self, Write};
+
+/// Size of the log ring buffer in bytes
+pub const LOG_BUF_SIZE: usize = 1 << 14;
+/// Records longer than this are truncated
+pub const LOG_LINE_MAX: usize = 256;
+
+/// A byte ring holding whole log records
+pub struct LogRing {
+    buf: [u8; LOG_BUF_SIZE],
+    /// number of bytes ever dropped, the position of the oldest byte
+    start: usize,
+    /// number of bytes ever written, the position after the newest byte
+    end: usize,
+}
+
+impl LogRing {
+    /// Create an empty ring
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; LOG_BUF_SIZE],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Append a record, which ends with a newline
+    pub fn push(&mut self, record: &[u8]) {
+        assert!(record.len() <= LOG_BUF_SIZE);
+        while self.end + record.len() - self.start > LOG_BUF_SIZE {
+            self.drop_record();
+        }
+        for byte in record {
+            self.buf[self.end % LOG_BUF_SIZE] = *byte;
+            self.end += 1;
+        }
+    }
+
+    fn drop_record(&mut self) {
+        while self.start < self.end {
+            let byte = self.buf[self.start % LOG_BUF_SIZE];
+            self.start += 1;
+            if byte == b'\n' {
+                break;
+            }
+        }
+    }
+
+    /// Number of bytes in the ring
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Copy the newest whole records fitting in `buf`, oldest first, and
+    /// return the number of bytes copied
+    pub fn read(&self, buf: &mut [u8]) -> usize {
+        let mut start = self.start;
+        if self.len() > buf.len() {
+            // skip the first record which would only partly fit
+            start = self.end - buf.len();
+            while start < self.end && self.buf[(start - 1) % LOG_BUF_SIZE] != b'\n' {
+                start += 1;
+            }
+        }
+        for (i, pos) in (start..self.end).enumerate() {
+            buf[i] = self.buf[pos % LOG_BUF_SIZE];
+        }
+        self.end - start
+    }
+
+    /// Drop every record
+    pub fn clear(&mut self) {
+        self.start = self.end;
+    }
+}
+
+/// A fixed buffer a record is formatted into before entering the ring,
+/// truncating what does not fit
+pub struct LineBuffer {
+    buf: [u8; LOG_LINE_MAX],
+    len: usize,
+}
+
+impl LineBuffer {
+    /// Create an empty line
+    pub fn new() -> Self {
+        Self {
+            buf: [0; LOG_LINE_MAX],
+            len: 0,
+        }
+    }
+
+    /// The line, ended with a newline even if truncated
+    pub fn finish(&mut self) -> &[u8] {
+        if self.len == LOG_LINE_MAX {
+            self.len -= 1;
+        }
+        self.buf[self.len] = b'\n';
+        &self.buf[..self.len + 1]
+    }
+}
+
+impl Write for LineBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let len = s.len().min(LOG_LINE_MAX - self.len);
+        self.buf[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn full_ring_drops_oldest_records() {
+        let mut ring = LogRing::new();
+        let mut line = [b'x'; LOG_LINE_MAX];
+        line[LOG_LINE_MAX - 1] = b'\n';
+        for _ in 0..LOG_BUF_SIZE / LOG_LINE_MAX {
+            ring.push(&line);
+        }
+        assert_eq!(ring.len(), LOG_BUF_SIZE);
+        ring.push(b"new\n");
+        assert_eq!(ring.len(), LOG_BUF_SIZE - LOG_LINE_MAX + 4);
+    }
+
+    #[test_case]
+    fn read_returns_newest_whole_records() {
+        let mut ring = LogRing::new();
+        ring.push(b"first\n");
+        ring.push(b"second\n");
+        let mut buf = [0; 10];
+        let read = ring.read(&mut buf);
+        assert_eq!(&buf[..read], b"second\n");
+        ring.clear();
+        assert_eq!(ring.read(&mut buf), 0);
+    }
+}
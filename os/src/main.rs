@@ -20,19 +20,30 @@
 #![no_std]
 #![no_main]
 #![feature(panic_info_message)]
+#![feature(custom_test_frameworks)]
+#![test_runner(crate::ktest::test_runner)]
+#![reexport_test_harness_main = "test_main"]
 
 use core::arch::global_asm;
 use log::*;
 
+extern crate alloc;
+
 #[path = "boards/qemu.rs"]
 mod board;
 
 #[macro_use]
 mod console;
+mod backtrace;
 mod config;
+mod drivers;
+mod fs;
+#[cfg(test)]
+mod ktest;
 mod lang_items;
 mod loader;
 mod logging;
+mod mm;
 mod sbi;
 mod sync;
 pub mod syscall;
@@ -41,7 +52,6 @@ mod timer;
 pub mod trap;
 
 global_asm!(include_str!("entry.asm"));
-global_asm!(include_str!("link_app.S"));
 
 /// clear BSS segment
 fn clear_bss() {
@@ -61,8 +71,12 @@ pub fn rust_main() -> ! {
     clear_bss();
     logging::init();
     info!("[kernel] Hello, world!");
+    mm::init();
     trap::init();
+    fs::list_apps();
     loader::load_apps();
+    #[cfg(test)]
+    test_main();
     trap::enable_timer_interrupt();
     timer::set_next_trigger();
     task::run_first_task();
@@ -0,0 +1,154 @@
+//! Anonymous memory mapped by apps
+//!
+//! Without paging, all apps share the physical address space, so memory
+//! cannot be mapped anywhere an app asks. Apps map pages of a fixed region
+//! after the app slots instead, from [`USER_MMAP_BASE`] on, at page-aligned
+//! addresses of their choice or where the kernel finds free pages. A page
+//! belongs to at most one process at a time: requests overlapping pages
+//! mapped by any process are refused, and the pages of a process are
+//! unmapped when it exits.
+//!
+//! Permissions are checked but not enforced. Without page tables, every app
+//! can read, write and execute any memory, including the pages mapped by
+//! other apps, until apps run in address spaces of their own.
+
+use crate::config::{PAGE_SIZE, USER_MMAP_BASE, USER_MMAP_SIZE};
+use crate::sync::UPSafeCell;
+use bitflags::*;
+use core::ops::Range;
+
+bitflags! {
+    /// Permissions of mapped memory, as the `prot` of `sys_mmap`
+    pub struct MapPermission: usize {
+        /// Readable
+        const R = 1 << 0;
+        /// Writable
+        const W = 1 << 1;
+        /// Executable
+        const X = 1 << 2;
+    }
+}
+
+/// Number of pages in the region
+const USER_MMAP_PAGES: usize = USER_MMAP_SIZE / PAGE_SIZE;
+
+/// Pid of the process owning each page of the region, if any
+struct UserPages {
+    pages: [Option<usize>; USER_MMAP_PAGES],
+}
+
+static USER_PAGES: UPSafeCell<UserPages> = unsafe {
+    UPSafeCell::new(UserPages {
+        pages: [None; USER_MMAP_PAGES],
+    })
+};
+
+/// Indices in the region of the pages of `[start, start + len)`, with `len`
+/// rounded up to whole pages, or `None` unless `start` is page aligned and
+/// the range is not empty and lies within the region
+fn page_range(start: usize, len: usize) -> Option<Range<usize>> {
+    if start % PAGE_SIZE != 0 || len == 0 || start < USER_MMAP_BASE {
+        return None;
+    }
+    let first = (start - USER_MMAP_BASE) / PAGE_SIZE;
+    let end = first.checked_add(len.div_ceil(PAGE_SIZE))?;
+    (end <= USER_MMAP_PAGES).then_some(first..end)
+}
+
+/// Indices in the region of the lowest pages in a row that are free and
+/// hold `len` bytes, if any
+fn free_page_range(pages: &[Option<usize>], len: usize) -> Option<Range<usize>> {
+    let count = len.div_ceil(PAGE_SIZE);
+    if count == 0 || count > pages.len() {
+        return None;
+    }
+    (0..=pages.len() - count)
+        .map(|first| first..first + count)
+        .find(|range| pages[range.clone()].iter().all(Option::is_none))
+}
+
+/// Map zeroed pages at `[start, start + len)` for process `pid`, or at the
+/// lowest free pages if `start` is 0. Return where they were mapped, or
+/// `None` if the range is invalid or any of its pages is mapped already.
+pub fn mmap(pid: usize, start: usize, len: usize) -> Option<usize> {
+    let mut user_pages = USER_PAGES.exclusive_access();
+    let range = if start == 0 {
+        free_page_range(&user_pages.pages, len)?
+    } else {
+        page_range(start, len)?
+    };
+    let pages = &mut user_pages.pages[range.clone()];
+    if pages.iter().any(Option::is_some) {
+        return None;
+    }
+    pages.fill(Some(pid));
+    let start = USER_MMAP_BASE + range.start * PAGE_SIZE;
+    unsafe {
+        core::ptr::write_bytes(start as *mut u8, 0, range.len() * PAGE_SIZE);
+    }
+    Some(start)
+}
+
+/// Unmap the pages at `[start, start + len)` of process `pid`. Return
+/// whether they were unmapped, which they are not if the range is invalid
+/// or any of its pages is not mapped by `pid`.
+pub fn munmap(pid: usize, start: usize, len: usize) -> bool {
+    let range = match page_range(start, len) {
+        Some(range) => range,
+        None => return false,
+    };
+    let mut user_pages = USER_PAGES.exclusive_access();
+    let pages = &mut user_pages.pages[range];
+    if !pages.iter().all(|page| *page == Some(pid)) {
+        return false;
+    }
+    pages.fill(None);
+    true
+}
+
+/// Unmap every page of process `pid`, as it exits
+pub fn munmap_all(pid: usize) {
+    for page in USER_PAGES.exclusive_access().pages.iter_mut() {
+        if *page == Some(pid) {
+            *page = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Not the pid of any app
+    const PID: usize = usize::MAX;
+
+    #[test_case]
+    fn overlapping_and_unaligned_requests_fail() {
+        let base = USER_MMAP_BASE;
+        assert_eq!(mmap(PID, base, 2 * PAGE_SIZE), Some(base));
+        assert_eq!(mmap(PID, base + PAGE_SIZE, PAGE_SIZE), None);
+        assert_eq!(mmap(PID, base + 2 * PAGE_SIZE + 1, PAGE_SIZE), None);
+        assert_eq!(mmap(PID, base + USER_MMAP_SIZE, PAGE_SIZE), None);
+        assert!(!munmap(PID - 1, base, PAGE_SIZE));
+        assert!(!munmap(PID, base, 3 * PAGE_SIZE));
+        assert!(munmap(PID, base, 1));
+        assert!(!munmap(PID, base, PAGE_SIZE));
+        munmap_all(PID);
+        assert_eq!(mmap(PID, base, 2 * PAGE_SIZE), Some(base));
+        munmap_all(PID);
+    }
+
+    #[test_case]
+    fn kernel_picks_the_lowest_free_pages() {
+        let base = USER_MMAP_BASE;
+        assert_eq!(
+            mmap(PID, base + PAGE_SIZE, PAGE_SIZE),
+            Some(base + PAGE_SIZE)
+        );
+        assert_eq!(mmap(PID, 0, PAGE_SIZE), Some(base));
+        assert_eq!(mmap(PID, 0, 2 * PAGE_SIZE), Some(base + 2 * PAGE_SIZE));
+        assert_eq!(mmap(PID, 0, USER_MMAP_SIZE), None);
+        assert_eq!(mmap(PID, 0, 0), None);
+        munmap_all(PID);
+    }
+}
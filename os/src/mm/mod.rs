@@ -0,0 +1,16 @@
+//! Memory management implementation
+//!
+//! For now the kernel runs on bare physical memory. Here are the kernel heap
+//! allocator backing `alloc` collections such as the wait queues of the
+//! kernel synchronization primitives, and the anonymous memory apps map with
+//! `sys_mmap`.
+
+mod heap_allocator;
+mod mmap;
+
+pub use mmap::{mmap, munmap, munmap_all, MapPermission};
+
+/// initiate the kernel heap allocator
+pub fn init() {
+    heap_allocator::init_heap();
+}
@@ -0,0 +1,56 @@
+//! Condition variables handed out to user applications
+
+use super::UPSafeCell;
+use crate::task::{block_current_and_run_next, current_task_id, wakeup_task};
+use alloc::collections::VecDeque;
+
+/// Condition variable structure
+pub struct Condvar {
+    /// Condition variable inner
+    pub inner: UPSafeCell<CondvarInner>,
+}
+
+/// Condition variable inner
+pub struct CondvarInner {
+    /// ids of the tasks waiting on this condition variable
+    pub wait_queue: VecDeque<usize>,
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Condvar {
+    /// Create a new condition variable
+    pub fn new() -> Self {
+        Self {
+            inner: unsafe {
+                UPSafeCell::new(CondvarInner {
+                    wait_queue: VecDeque::new(),
+                })
+            },
+        }
+    }
+
+    /// Signal a task waiting on the condition variable, skipping the tasks
+    /// which exited while waiting
+    pub fn signal(&self) {
+        let mut inner = self.inner.exclusive_access();
+        while let Some(task) = inner.wait_queue.pop_front() {
+            if wakeup_task(task) {
+                break;
+            }
+        }
+    }
+
+    /// blocking current task, let it wait on the condition variable until
+    /// signaled. The caller unlocks its mutex before and locks it again after.
+    pub fn wait(&self) {
+        let mut inner = self.inner.exclusive_access();
+        inner.wait_queue.push_back(current_task_id());
+        drop(inner);
+        block_current_and_run_next();
+    }
+}
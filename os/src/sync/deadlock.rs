@@ -0,0 +1,224 @@
+//! Banker's algorithm style deadlock avoidance
+//!
+//! Every mutex and semaphore is a resource with a number of units: one for
+//! a mutex, the initial count for a semaphore. Each process has a
+//! [`ResourceTracker`] for its own mutexes and semaphores, which follows how
+//! many units of each resource are available, allocated to each of its
+//! threads, and still needed by each of them, and tells whether granting a
+//! request leaves the process in a safe state.
+
+use alloc::{vec, vec::Vec};
+
+/// Available, allocation and need matrices of all resources
+pub struct ResourceTracker {
+    /// free units of each resource
+    available: Vec<usize>,
+    /// units of each resource held by each task
+    allocation: Vec<Vec<usize>>,
+    /// units of each resource each task is waiting for
+    need: Vec<Vec<usize>>,
+}
+
+impl Default for ResourceTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResourceTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self {
+            available: Vec::new(),
+            allocation: Vec::new(),
+            need: Vec::new(),
+        }
+    }
+
+    /// Register a resource with `units` free units and return its id
+    pub fn add_resource(&mut self, units: usize) -> usize {
+        self.available.push(units);
+        for row in self.allocation.iter_mut().chain(self.need.iter_mut()) {
+            row.push(0);
+        }
+        self.available.len() - 1
+    }
+
+    /// Make sure `task` has a row in the matrices
+    fn ensure_task(&mut self, task: usize) {
+        let resources = self.available.len();
+        while self.allocation.len() <= task {
+            self.allocation.push(vec![0; resources]);
+            self.need.push(vec![0; resources]);
+        }
+    }
+
+    /// Record that `task` asks for a unit of resource `res`.
+    ///
+    /// If `check` is set and the system would no longer be safe, the
+    /// request is withdrawn and `false` is returned.
+    pub fn request(&mut self, task: usize, res: usize, check: bool) -> bool {
+        self.ensure_task(task);
+        self.need[task][res] += 1;
+        if check && !self.is_safe() {
+            self.need[task][res] -= 1;
+            return false;
+        }
+        true
+    }
+
+    /// Withdraw a request of `task` for resource `res` which will not be
+    /// granted
+    pub fn withdraw(&mut self, task: usize, res: usize) {
+        self.ensure_task(task);
+        self.need[task][res] -= 1;
+    }
+
+    /// Record that the request of `task` for resource `res` was granted
+    pub fn acquire(&mut self, task: usize, res: usize) {
+        self.ensure_task(task);
+        self.need[task][res] -= 1;
+        self.allocation[task][res] += 1;
+        self.available[res] -= 1;
+    }
+
+    /// Record that `task` gives a unit of resource `res` back.
+    ///
+    /// A task may release units it never acquired, as a semaphore producer
+    /// does, in which case only the available count grows.
+    pub fn release(&mut self, task: usize, res: usize) {
+        self.ensure_task(task);
+        if self.allocation[task][res] > 0 {
+            self.allocation[task][res] -= 1;
+        }
+        self.available[res] += 1;
+    }
+
+    /// Record that `task` gives back a unit of resource `res` it holds, as
+    /// when unlocking a mutex. Return `false`, changing nothing, if it holds
+    /// none.
+    pub fn release_held(&mut self, task: usize, res: usize) -> bool {
+        self.ensure_task(task);
+        if self.allocation[task][res] == 0 {
+            return false;
+        }
+        self.allocation[task][res] -= 1;
+        self.available[res] += 1;
+        true
+    }
+
+    /// Forget what `task` holds and needs, as it exited.
+    ///
+    /// Units it held are not made available again: a mutex left locked by
+    /// an exited thread stays locked.
+    pub fn clear_task(&mut self, task: usize) {
+        if task < self.allocation.len() {
+            self.allocation[task].fill(0);
+            self.need[task].fill(0);
+        }
+    }
+
+    /// Safety check: is there an order in which every task can get what it
+    /// needs, run to completion and give back what it holds?
+    fn is_safe(&self) -> bool {
+        let mut work = self.available.clone();
+        let mut finish = vec![false; self.need.len()];
+        loop {
+            let next = (0..self.need.len()).find(|&task| {
+                !finish[task]
+                    && self.need[task]
+                        .iter()
+                        .zip(work.iter())
+                        .all(|(need, work)| need <= work)
+            });
+            match next {
+                Some(task) => {
+                    for (work, alloc) in work.iter_mut().zip(self.allocation[task].iter()) {
+                        *work += alloc;
+                    }
+                    finish[task] = true;
+                }
+                None => return finish.iter().all(|finished| *finished),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ResourceTracker;
+
+    #[test_case]
+    fn lock_order_inversion_is_refused() {
+        let mut tracker = ResourceTracker::new();
+        let a = tracker.add_resource(1);
+        let b = tracker.add_resource(1);
+        // task 0 holds a, task 1 holds b
+        assert!(tracker.request(0, a, true));
+        tracker.acquire(0, a);
+        assert!(tracker.request(1, b, true));
+        tracker.acquire(1, b);
+        // task 0 may wait for b, but then task 1 must not wait for a
+        assert!(tracker.request(0, b, true));
+        assert!(!tracker.request(1, a, true));
+        // without checking, the request is recorded anyway
+        assert!(tracker.request(1, a, false));
+    }
+
+    #[test_case]
+    fn semaphore_units_are_shared() {
+        let mut tracker = ResourceTracker::new();
+        let sem = tracker.add_resource(2);
+        for task in 0..2 {
+            assert!(tracker.request(task, sem, true));
+            tracker.acquire(task, sem);
+        }
+        tracker.release(0, sem);
+        assert!(tracker.request(2, sem, true));
+    }
+
+    #[test_case]
+    fn mutex_release_needs_a_holder() {
+        let mut tracker = ResourceTracker::new();
+        let mutex = tracker.add_resource(1);
+        // a stray unlock must not make the mutex look free twice
+        assert!(!tracker.release_held(1, mutex));
+        assert!(tracker.request(0, mutex, true));
+        tracker.acquire(0, mutex);
+        assert!(tracker.release_held(0, mutex));
+        assert!(!tracker.release_held(0, mutex));
+        assert!(tracker.request(0, mutex, true));
+        tracker.acquire(0, mutex);
+        tracker.clear_task(0);
+        assert!(!tracker.request(1, mutex, true));
+    }
+
+    #[test_case]
+    fn exited_tasks_are_forgotten() {
+        let mut tracker = ResourceTracker::new();
+        let mutex = tracker.add_resource(1);
+        assert!(tracker.request(0, mutex, true));
+        tracker.acquire(0, mutex);
+        // while task 0 may still unlock it, waiting for the mutex is safe
+        assert!(tracker.request(1, mutex, true));
+        // once task 0 exited holding it, it will never be unlocked
+        tracker.clear_task(0);
+        tracker.clear_task(3);
+        assert!(!tracker.request(2, mutex, true));
+    }
+
+    #[test_case]
+    fn withdrawn_requests_are_forgotten() {
+        let mut tracker = ResourceTracker::new();
+        let mutex = tracker.add_resource(1);
+        assert!(tracker.request(0, mutex, true));
+        tracker.acquire(0, mutex);
+        // task 0 locking the mutex again would wait for itself
+        assert!(!tracker.request(0, mutex, true));
+        assert!(tracker.request(0, mutex, false));
+        assert!(!tracker.request(1, mutex, true));
+        // refused by the mutex, the request leaves task 0 able to unlock it
+        tracker.withdraw(0, mutex);
+        assert!(tracker.request(1, mutex, true));
+    }
+}
@@ -1,5 +1,13 @@
 //! Synchronization and interior mutability primitives
 
+mod condvar;
+mod deadlock;
+mod mutex;
+mod semaphore;
 mod up;
 
+pub use condvar::Condvar;
+pub use deadlock::ResourceTracker;
+pub use mutex::{Mutex, MutexBlocking, MutexSpin};
+pub use semaphore::Semaphore;
 pub use up::UPSafeCell;
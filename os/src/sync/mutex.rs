@@ -0,0 +1,134 @@
+//! Mutex locks handed out to user applications
+
+use super::UPSafeCell;
+use crate::task::{
+    block_current_and_run_next, current_task_id, suspend_current_and_run_next, wakeup_task,
+};
+use alloc::collections::VecDeque;
+
+/// Mutex trait
+pub trait Mutex: Sync + Send {
+    /// Lock the mutex for the current task, or return `false`, changing
+    /// nothing, if the current task holds it already
+    fn lock(&self) -> bool;
+    /// Unlock the mutex, or return `false` if the current task does not
+    /// hold it
+    fn unlock(&self) -> bool;
+}
+
+/// Spinlock Mutex struct
+///
+/// A task that fails to get the lock yields its time slice and tries again
+/// the next time it is scheduled.
+pub struct MutexSpin {
+    /// id of the task holding the lock
+    owner: UPSafeCell<Option<usize>>,
+}
+
+impl MutexSpin {
+    /// Create a new spinlock mutex
+    pub fn new() -> Self {
+        Self {
+            owner: unsafe { UPSafeCell::new(None) },
+        }
+    }
+}
+
+impl Mutex for MutexSpin {
+    /// Lock the spinlock mutex
+    fn lock(&self) -> bool {
+        let current = current_task_id();
+        loop {
+            let mut owner = self.owner.exclusive_access();
+            match *owner {
+                // yielding would wait for ourselves forever
+                Some(task) if task == current => return false,
+                Some(_) => {
+                    drop(owner);
+                    suspend_current_and_run_next();
+                }
+                None => {
+                    *owner = Some(current);
+                    return true;
+                }
+            }
+        }
+    }
+
+    fn unlock(&self) -> bool {
+        let mut owner = self.owner.exclusive_access();
+        if *owner != Some(current_task_id()) {
+            return false;
+        }
+        *owner = None;
+        true
+    }
+}
+
+/// Blocking Mutex struct
+///
+/// A task that fails to get the lock is blocked in the wait queue of the
+/// mutex, and the lock is handed over to it directly on unlock.
+pub struct MutexBlocking {
+    inner: UPSafeCell<MutexBlockingInner>,
+}
+
+/// Inner of MutexBlocking
+pub struct MutexBlockingInner {
+    /// id of the task holding the lock
+    owner: Option<usize>,
+    wait_queue: VecDeque<usize>,
+}
+
+impl MutexBlocking {
+    /// Create a new blocking mutex
+    pub fn new() -> Self {
+        Self {
+            inner: unsafe {
+                UPSafeCell::new(MutexBlockingInner {
+                    owner: None,
+                    wait_queue: VecDeque::new(),
+                })
+            },
+        }
+    }
+}
+
+impl Mutex for MutexBlocking {
+    /// lock the blocking mutex
+    fn lock(&self) -> bool {
+        let current = current_task_id();
+        let mut mutex_inner = self.inner.exclusive_access();
+        match mutex_inner.owner {
+            // blocking would leave us waiting for ourselves forever
+            Some(task) if task == current => false,
+            Some(_) => {
+                mutex_inner.wait_queue.push_back(current);
+                drop(mutex_inner);
+                block_current_and_run_next();
+                true
+            }
+            None => {
+                mutex_inner.owner = Some(current);
+                true
+            }
+        }
+    }
+
+    /// unlock the blocking mutex, handing it over to the first waiting task
+    /// which has not exited
+    fn unlock(&self) -> bool {
+        let mut mutex_inner = self.inner.exclusive_access();
+        if mutex_inner.owner != Some(current_task_id()) {
+            return false;
+        }
+        mutex_inner.owner = None;
+        while let Some(waking_task) = mutex_inner.wait_queue.pop_front() {
+            if wakeup_task(waking_task) {
+                mutex_inner.owner = Some(waking_task);
+                break;
+            }
+        }
+        true
+    }
+}
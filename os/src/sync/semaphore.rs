@@ -0,0 +1,61 @@
+//! Counting semaphores handed out to user applications
+
+use super::UPSafeCell;
+use crate::task::{block_current_and_run_next, current_task_id, wakeup_task};
+use alloc::collections::VecDeque;
+
+/// Semaphore struct
+pub struct Semaphore {
+    /// Semaphore inner
+    pub inner: UPSafeCell<SemaphoreInner>,
+}
+
+/// Semaphore inner
+pub struct SemaphoreInner {
+    /// available resources, or the number of waiting tasks if negative
+    pub count: isize,
+    /// ids of the tasks blocked on this semaphore
+    pub wait_queue: VecDeque<usize>,
+}
+
+impl Semaphore {
+    /// Create a new semaphore
+    pub fn new(res_count: usize) -> Self {
+        Self {
+            inner: unsafe {
+                UPSafeCell::new(SemaphoreInner {
+                    count: res_count as isize,
+                    wait_queue: VecDeque::new(),
+                })
+            },
+        }
+    }
+
+    /// up operation of semaphore
+    ///
+    /// The resource goes to the first waiting task which has not exited. A
+    /// task that exited while waiting gives back the resource it was
+    /// counted for.
+    pub fn up(&self) {
+        let mut inner = self.inner.exclusive_access();
+        inner.count += 1;
+        while inner.count <= 0 {
+            match inner.wait_queue.pop_front() {
+                Some(task) if wakeup_task(task) => break,
+                Some(_) => inner.count += 1,
+                None => break,
+            }
+        }
+    }
+
+    /// down operation of semaphore
+    pub fn down(&self) {
+        let mut inner = self.inner.exclusive_access();
+        inner.count -= 1;
+        if inner.count < 0 {
+            inner.wait_queue.push_back(current_task_id());
+            drop(inner);
+            block_current_and_run_next();
+        }
+    }
+}
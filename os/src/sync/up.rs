@@ -19,7 +19,7 @@ unsafe impl<T> Sync for UPSafeCell<T> {}
 impl<T> UPSafeCell<T> {
     /// User is responsible to guarantee that inner struct is only used in
     /// uniprocessor.
-    pub unsafe fn new(value: T) -> Self {
+    pub const unsafe fn new(value: T) -> Self {
         Self {
             inner: RefCell::new(value),
         }
@@ -29,3 +29,15 @@ impl<T> UPSafeCell<T> {
         self.inner.borrow_mut()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::UPSafeCell;
+
+    #[test_case]
+    fn exclusive_access_sees_writes() {
+        let cell = unsafe { UPSafeCell::new(1) };
+        *cell.exclusive_access() += 1;
+        assert_eq!(*cell.exclusive_access(), 2);
+    }
+}
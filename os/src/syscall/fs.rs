@@ -1,18 +1,129 @@
 //! File and filesystem-related syscalls
+use crate::fs::{make_pipe, open_file, open_initramfs_file, File, OpenFlags, Stat, ROOT_INODE};
+use crate::task::{add_current_file, get_current_file, take_current_file};
+use alloc::string::String;
+use alloc::sync::Arc;
 
-const FD_STDOUT: usize = 1;
+/// Copy the NUL-terminated string at `ptr` of the shared address space
+pub(super) fn user_str(ptr: *const u8) -> String {
+    let mut string = String::new();
+    let mut ptr = ptr;
+    loop {
+        let ch = unsafe { *ptr };
+        if ch == 0 {
+            break;
+        }
+        string.push(ch as char);
+        ptr = unsafe { ptr.add(1) };
+    }
+    string
+}
 
-/// write buf of length `len`  to a file with `fd`
+/// write buf of length `len`  to a file with `fd`, return how many bytes
+/// were written, fewer than `len` if the disk got full
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
-    match fd {
-        FD_STDOUT => {
-            let slice = unsafe { core::slice::from_raw_parts(buf, len) };
-            let str = core::str::from_utf8(slice).unwrap();
-            print!("{}", str);
-            len as isize
+    match get_current_file(fd) {
+        Some(file) if file.writable() => {
+            let buf = unsafe { core::slice::from_raw_parts(buf, len) };
+            file.write(buf) as isize
         }
-        _ => {
-            panic!("Unsupported fd in sys_write!");
+        _ => -1,
+    }
+}
+
+/// read up to `len` bytes into buf from a file with `fd`
+pub fn sys_read(fd: usize, buf: *mut u8, len: usize) -> isize {
+    match get_current_file(fd) {
+        Some(file) if file.readable() => {
+            let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+            file.read(buf) as isize
         }
+        _ => -1,
+    }
+}
+
+/// open the file named `path` with `flags`, return its file descriptor.
+/// Absolute paths are looked up in the initramfs, which is read only.
+pub fn sys_open(path: *const u8, flags: u32) -> isize {
+    let path = user_str(path);
+    let flags = match OpenFlags::from_bits(flags) {
+        Some(flags) => flags,
+        None => return -1,
+    };
+    let file: Option<Arc<dyn File>> = if path.starts_with('/') {
+        if flags.modifies() {
+            return -1;
+        }
+        open_initramfs_file(path.as_str()).map(|file| file as Arc<dyn File>)
+    } else {
+        open_file(path.as_str(), flags).map(|inode| inode as Arc<dyn File>)
+    };
+    match file {
+        Some(file) => add_current_file(file) as isize,
+        None => -1,
+    }
+}
+
+/// close the file with `fd`
+pub fn sys_close(fd: usize) -> isize {
+    match take_current_file(fd) {
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+/// create a pipe, then write the file descriptors of its read end and write
+/// end into `pipe[0]` and `pipe[1]`
+pub fn sys_pipe(pipe: *mut usize) -> isize {
+    let (pipe_read, pipe_write) = make_pipe();
+    let read_fd = add_current_file(pipe_read);
+    let write_fd = add_current_file(pipe_write);
+    unsafe {
+        *pipe = read_fd;
+        *pipe.add(1) = write_fd;
+    }
+    0
+}
+
+/// open the file with `fd` again under the lowest free file descriptor
+pub fn sys_dup(fd: usize) -> isize {
+    match get_current_file(fd) {
+        Some(file) => add_current_file(file) as isize,
+        None => -1,
+    }
+}
+
+/// write the status of the file with `fd` to `st`
+pub fn sys_fstat(fd: usize, st: *mut Stat) -> isize {
+    match get_current_file(fd).and_then(|file| file.stat()) {
+        Some(stat) => {
+            unsafe {
+                *st = stat;
+            }
+            0
+        }
+        None => -1,
+    }
+}
+
+/// create `new_path` as a hard link to the file `old_path`
+pub fn sys_linkat(old_path: *const u8, new_path: *const u8) -> isize {
+    let old_path = user_str(old_path);
+    let new_path = user_str(new_path);
+    if ROOT_INODE.link(old_path.as_str(), new_path.as_str()) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// remove the link `path`, and the file with its last link once it is no
+/// longer open
+pub fn sys_unlinkat(path: *const u8) -> isize {
+    let path = user_str(path);
+    if ROOT_INODE.unlink(path.as_str()) {
+        0
+    } else {
+        -1
     }
 }
@@ -1,11 +1,31 @@
 //! Process management syscalls
-use crate::task::{exit_current_and_run_next, suspend_current_and_run_next};
+use crate::mm::{mmap, munmap, MapPermission};
+use crate::task::{
+    change_current_brk, current_pid, current_tid, exit_current_and_run_next,
+    suspend_current_and_run_next,
+};
 use crate::timer::get_time_ms;
+use log::*;
+
+/// Exit code of a user thread that panicked
+const PANIC_EXIT_CODE: i32 = 101;
 
 /// task exits and submit an exit code
+///
+/// When the main thread exits, the whole application exits with it, and
+/// any code but 0 is reported as an abnormal termination.
 pub fn sys_exit(exit_code: i32) -> ! {
-    println!("[kernel] Application exited with code {}", exit_code);
-    exit_current_and_run_next();
+    if current_tid() == 0 {
+        match exit_code {
+            0 => info!("[kernel] Application exited with code 0"),
+            PANIC_EXIT_CODE => warn!("[kernel] Application panicked, exit code {}", exit_code),
+            _ => warn!(
+                "[kernel] Application terminated abnormally with code {}",
+                exit_code
+            ),
+        }
+    }
+    exit_current_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
 }
 
@@ -19,3 +39,39 @@ pub fn sys_yield() -> isize {
 pub fn sys_get_time() -> isize {
     get_time_ms() as isize
 }
+
+/// move the end of the heap by `increment` bytes, which may be negative, and
+/// return the old end
+pub fn sys_sbrk(increment: isize) -> isize {
+    match change_current_brk(increment) {
+        Some(old_brk) => old_brk as isize,
+        None => -1,
+    }
+}
+
+/// map `len` bytes of zeroed memory at the page-aligned address `start`, or
+/// where the kernel finds room if `start` is 0, and return its address.
+///
+/// `prot` must be a non-empty set of R (1), W (2) and X (4), but it is not
+/// enforced: without page tables, the pages come from one physical region
+/// shared by all apps, which every app can read, write and execute, whoever
+/// mapped them.
+pub fn sys_mmap(start: usize, len: usize, prot: usize) -> isize {
+    match MapPermission::from_bits(prot) {
+        Some(prot) if !prot.is_empty() => {}
+        _ => return -1,
+    }
+    match mmap(current_pid(), start, len) {
+        Some(start) => start as isize,
+        None => -1,
+    }
+}
+
+/// unmap `len` bytes of memory mapped at `start` by the current process
+pub fn sys_munmap(start: usize, len: usize) -> isize {
+    if munmap(current_pid(), start, len) {
+        0
+    } else {
+        -1
+    }
+}
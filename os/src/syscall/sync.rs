@@ -0,0 +1,215 @@
+//! Synchronization syscalls
+//!
+//! Mutexes, semaphores and condition variables are kernel objects belonging
+//! to the process creating them, and shared by its threads. They are
+//! referred to by their index in the tables of the process, handed out in
+//! creation order and reusing no slot, and dropped when the process exits.
+//!
+//! Every mutex and semaphore is also a resource of the deadlock tracker of
+//! its process, so that processes which turned on deadlock detection get
+//! lock and down requests refused when granting them could lead to a
+//! deadlock between their threads.
+
+use crate::sync::{Condvar, Mutex, MutexBlocking, MutexSpin, Semaphore};
+use crate::task::{current_tid, with_current_process};
+use alloc::sync::Arc;
+
+/// returned by lock and down requests refused by deadlock detection, and
+/// by locks of a mutex by the thread holding it
+const EDEADLK: isize = -0xDEAD;
+
+/// Look up a mutex, releasing the table before the caller may block on it
+fn get_mutex(mutex_id: usize) -> Option<(Arc<dyn Mutex>, usize)> {
+    with_current_process(|process| process.mutex_list.get(mutex_id).cloned())
+}
+
+/// Look up a semaphore, releasing the table before the caller may block on it
+fn get_semaphore(sem_id: usize) -> Option<(Arc<Semaphore>, usize)> {
+    with_current_process(|process| process.semaphore_list.get(sem_id).cloned())
+}
+
+/// Look up a condition variable, releasing the table before the caller may block on it
+fn get_condvar(condvar_id: usize) -> Option<Arc<Condvar>> {
+    with_current_process(|process| process.condvar_list.get(condvar_id).cloned())
+}
+
+/// Record a request of current thread for resource `res`, checking it if
+/// its process turned on deadlock detection
+fn request_resource(res: usize) -> bool {
+    let tid = current_tid();
+    with_current_process(|process| {
+        let check = process.deadlock_detect;
+        process.resources.request(tid, res, check)
+    })
+}
+
+/// Withdraw a request of current thread for resource `res`, which it will
+/// not get
+fn withdraw_resource(res: usize) {
+    let tid = current_tid();
+    with_current_process(|process| process.resources.withdraw(tid, res));
+}
+
+/// Record that current thread got resource `res`
+fn acquire_resource(res: usize) {
+    let tid = current_tid();
+    with_current_process(|process| process.resources.acquire(tid, res));
+}
+
+/// Record that current thread gave a unit of semaphore resource `res` back
+fn release_resource(res: usize) {
+    let tid = current_tid();
+    with_current_process(|process| process.resources.release(tid, res));
+}
+
+/// Record that current thread unlocked mutex resource `res`, which it holds
+fn release_held_resource(res: usize) {
+    let tid = current_tid();
+    let held = with_current_process(|process| process.resources.release_held(tid, res));
+    debug_assert!(held, "unlocked mutex resource {} not held", res);
+}
+
+/// Lock `mutex`, which is resource `res`, once deadlock detection agreed
+fn lock_mutex(mutex: &Arc<dyn Mutex>, res: usize) -> isize {
+    if !request_resource(res) {
+        return EDEADLK;
+    }
+    if !mutex.lock() {
+        withdraw_resource(res);
+        return EDEADLK;
+    }
+    acquire_resource(res);
+    0
+}
+
+/// create a mutex, blocking or spinning on contention, and return its id
+pub fn sys_mutex_create(blocking: bool) -> isize {
+    let mutex: Arc<dyn Mutex> = if blocking {
+        Arc::new(MutexBlocking::new())
+    } else {
+        Arc::new(MutexSpin::new())
+    };
+    with_current_process(|process| {
+        let res = process.resources.add_resource(1);
+        process.mutex_list.push((mutex, res));
+        process.mutex_list.len() as isize - 1
+    })
+}
+
+/// lock the mutex with `mutex_id`, return -1 if it does not exist or
+/// -0xDEAD if deadlock detection refused the request or current thread
+/// holds it already
+pub fn sys_mutex_lock(mutex_id: usize) -> isize {
+    match get_mutex(mutex_id) {
+        Some((mutex, res)) => lock_mutex(&mutex, res),
+        None => -1,
+    }
+}
+
+/// unlock the mutex with `mutex_id`, return -1 if it does not exist or
+/// current task does not hold it
+pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
+    match get_mutex(mutex_id) {
+        Some((mutex, res)) => {
+            if !mutex.unlock() {
+                return -1;
+            }
+            release_held_resource(res);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// create a semaphore holding `res_count` resources and return its id.
+///
+/// With deadlock detection on, a down on a semaphore holding no resource is
+/// refused unless another thread holds one to give back: the tracker cannot
+/// know that some thread will up a semaphore created with a count of 0.
+pub fn sys_semaphore_create(res_count: usize) -> isize {
+    with_current_process(|process| {
+        let res = process.resources.add_resource(res_count);
+        process
+            .semaphore_list
+            .push((Arc::new(Semaphore::new(res_count)), res));
+        process.semaphore_list.len() as isize - 1
+    })
+}
+
+/// release a resource of the semaphore with `sem_id`, return -1 if it does not exist
+pub fn sys_semaphore_up(sem_id: usize) -> isize {
+    match get_semaphore(sem_id) {
+        Some((sem, res)) => {
+            release_resource(res);
+            sem.up();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// acquire a resource of the semaphore with `sem_id`, return -1 if it does
+/// not exist or -0xDEAD if deadlock detection refused the request
+pub fn sys_semaphore_down(sem_id: usize) -> isize {
+    match get_semaphore(sem_id) {
+        Some((sem, res)) => {
+            if !request_resource(res) {
+                return EDEADLK;
+            }
+            sem.down();
+            acquire_resource(res);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// create a condition variable and return its id
+pub fn sys_condvar_create() -> isize {
+    with_current_process(|process| {
+        process.condvar_list.push(Arc::new(Condvar::new()));
+        process.condvar_list.len() as isize - 1
+    })
+}
+
+/// wake up a task waiting on the condition variable with `condvar_id`,
+/// return -1 if it does not exist
+pub fn sys_condvar_signal(condvar_id: usize) -> isize {
+    match get_condvar(condvar_id) {
+        Some(condvar) => {
+            condvar.signal();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// release the mutex with `mutex_id` and wait on the condition variable with
+/// `condvar_id`, locking the mutex again once woken up. Return -1 if either
+/// does not exist or current task does not hold the mutex, or -0xDEAD,
+/// without the mutex, if deadlock detection refused locking it again.
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    match (get_condvar(condvar_id), get_mutex(mutex_id)) {
+        (Some(condvar), Some((mutex, res))) => {
+            if !mutex.unlock() {
+                return -1;
+            }
+            release_held_resource(res);
+            condvar.wait();
+            lock_mutex(&mutex, res)
+        }
+        _ => -1,
+    }
+}
+
+/// turn deadlock detection of current process on (1) or off (0),
+/// return -1 for any other value
+pub fn sys_enable_deadlock_detect(enabled: usize) -> isize {
+    match enabled {
+        0 | 1 => {
+            with_current_process(|process| process.deadlock_detect = enabled == 1);
+            0
+        }
+        _ => -1,
+    }
+}
@@ -0,0 +1,67 @@
+//! Kernel log syscalls
+use super::fs::user_str;
+use crate::logging::{set_console_level, set_log_filter, LogFilter, LOG_BUF_SIZE, LOG_RING};
+use log::LevelFilter;
+
+const SYSLOG_ACTION_READ_ALL: usize = 3;
+const SYSLOG_ACTION_READ_CLEAR: usize = 4;
+const SYSLOG_ACTION_CLEAR: usize = 5;
+const SYSLOG_ACTION_CONSOLE_LEVEL: usize = 8;
+const SYSLOG_ACTION_SIZE_UNREAD: usize = 9;
+const SYSLOG_ACTION_SIZE_BUFFER: usize = 10;
+
+/// operate on the kernel log, following the `type` actions of Linux
+/// `syslog(2)` we support:
+///
+/// - 3: copy the newest whole records fitting in `buf` of length `len`
+/// - 4: same as 3, then clear the log
+/// - 5: clear the log
+/// - 8: echo records up to level `len` to the console, 0 for none and 1
+///   (error) to 5 (trace)
+/// - 9: number of bytes in the log
+/// - 10: size of the log buffer
+pub fn sys_syslog(log_type: usize, buf: *mut u8, len: usize) -> isize {
+    match log_type {
+        SYSLOG_ACTION_READ_ALL | SYSLOG_ACTION_READ_CLEAR => {
+            let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+            let mut ring = LOG_RING.exclusive_access();
+            let read = ring.read(buf);
+            if log_type == SYSLOG_ACTION_READ_CLEAR {
+                ring.clear();
+            }
+            read as isize
+        }
+        SYSLOG_ACTION_CLEAR => {
+            LOG_RING.exclusive_access().clear();
+            0
+        }
+        SYSLOG_ACTION_CONSOLE_LEVEL => {
+            let level = match len {
+                0 => LevelFilter::Off,
+                1 => LevelFilter::Error,
+                2 => LevelFilter::Warn,
+                3 => LevelFilter::Info,
+                4 => LevelFilter::Debug,
+                5 => LevelFilter::Trace,
+                _ => return -1,
+            };
+            set_console_level(level);
+            0
+        }
+        SYSLOG_ACTION_SIZE_UNREAD => LOG_RING.exclusive_access().len() as isize,
+        SYSLOG_ACTION_SIZE_BUFFER => LOG_BUF_SIZE as isize,
+        _ => -1,
+    }
+}
+
+/// replace the kernel log filter with the NUL-terminated `spec`, written
+/// like the `LOG` build variable, as in `info,task=trace,trap=warn`
+pub fn sys_set_log_filter(spec: *const u8) -> isize {
+    match LogFilter::parse(user_str(spec).as_str()) {
+        Some(filter) => {
+            set_log_filter(filter);
+            0
+        }
+        None => -1,
+    }
+}
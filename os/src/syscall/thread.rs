@@ -0,0 +1,34 @@
+//! Thread management syscalls
+use crate::task::{create_thread, current_tid, waittid};
+
+/// create a thread of the current app starting at `entry` with `arg` in
+/// `a0`, and return its thread id
+///
+/// The thread function must end by calling `sys_exit` itself, as there is
+/// nothing to return to.
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    create_thread(entry, arg) as isize
+}
+
+/// get the thread id of the current thread
+pub fn sys_gettid() -> isize {
+    current_tid() as isize
+}
+
+/// write the exit code of thread `tid` of the current app to `exit_code`
+/// once it exited
+///
+/// Return 0 on success, -1 if there is no such thread or it is the calling
+/// thread, and -2 if it is still running. The exit code is not returned
+/// itself, as any value, -1 and -2 included, is a valid one.
+pub fn sys_waittid(tid: usize, exit_code: *mut i32) -> isize {
+    match waittid(tid) {
+        Ok(code) => {
+            unsafe {
+                *exit_code = code;
+            }
+            0
+        }
+        Err(err) => err,
+    }
+}
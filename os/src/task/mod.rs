@@ -4,7 +4,13 @@
 //! implemented here.
 //!
 //! A single global instance of [`TaskManager`] called `TASK_MANAGER` controls
-//! all the tasks in the operating system.
+//! all the tasks in the operating system. Task statuses and the choice of
+//! the next task to run are left to its `task_sched::Scheduler`, which
+//! builds and is property-tested on the host.
+//!
+//! A task is a thread. Every app is a process starting with a single main
+//! thread, and may create more threads sharing its memory. All threads are
+//! scheduled independently, and the whole process ends with its main thread.
 //!
 //! Be careful when you see `__switch` ASM function in `switch.S`. Control flow around this function
 //! might not be what you expect.
@@ -15,13 +21,20 @@ mod switch;
 #[allow(clippy::module_inception)]
 mod task;
 
-use crate::config::MAX_APP_NUM;
-use crate::loader::{get_num_app, init_app_cx};
+use crate::fs::File;
+use crate::loader::{get_app_name, get_num_app, init_app_cx, ThreadStack};
+use crate::mm::munmap_all;
 use crate::sbi::shutdown;
-use crate::sync::UPSafeCell;
+use crate::sync::{ResourceTracker, UPSafeCell};
+use crate::trap::TrapContext;
+use alloc::{sync::Arc, vec::Vec};
+use core::cell::RefMut;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use lazy_static::*;
+use log::*;
 use switch::__switch;
-use task::{TaskControlBlock, TaskStatus};
+use task::{ProcessControlBlock, TaskControlBlock};
+use task_sched::{Schedule, Scheduler, TaskStatus};
 
 pub use context::TaskContext;
 
@@ -35,44 +48,87 @@ pub use context::TaskContext;
 /// borrowing checks to runtime. You can see examples on how to use `inner` in
 /// existing functions on `TaskManager`.
 pub struct TaskManager {
-    /// total number of tasks
-    num_app: usize,
     /// use inner value to get mutable access
     inner: UPSafeCell<TaskManagerInner>,
 }
 
 /// Inner of Task Manager
 pub struct TaskManagerInner {
-    /// task list
-    tasks: [TaskControlBlock; MAX_APP_NUM],
-    /// id of current `Running` task
-    current_task: usize,
+    /// task list, where the id of a task is its index
+    tasks: Vec<TaskControlBlock>,
+    /// process list, where the id of a process is the id of its app
+    processes: Vec<ProcessControlBlock>,
+    /// status of every task and the current one
+    sched: Scheduler,
 }
 
 lazy_static! {
     /// Global variable: TASK_MANAGER
     pub static ref TASK_MANAGER: TaskManager = {
         let num_app = get_num_app();
-        let mut tasks = [TaskControlBlock {
-            task_cx: TaskContext::zero_init(),
-            task_status: TaskStatus::UnInit,
-        }; MAX_APP_NUM];
-        for (i, task) in tasks.iter_mut().enumerate() {
-            task.task_cx = TaskContext::goto_restore(init_app_cx(i));
-            task.task_status = TaskStatus::Ready;
+        // the main thread of app i is task i
+        let tasks = (0..num_app)
+            .map(|i| TaskControlBlock {
+                pid: i,
+                tid: 0,
+                task_cx: TaskContext::goto_restore(init_app_cx(i)),
+                stack: None,
+                exit_code: None,
+            })
+            .collect();
+        let processes = (0..num_app).map(ProcessControlBlock::new).collect();
+        let mut sched = Scheduler::new(num_app);
+        if USER_TESTS {
+            // only the test suite runs, the other apps are skipped
+            for app_id in (0..num_app).filter(|i| !is_user_test(get_app_name(*i))) {
+                sched.exit(app_id);
+            }
         }
         TaskManager {
-            num_app,
             inner: unsafe {
                 UPSafeCell::new(TaskManagerInner {
                     tasks,
-                    current_task: 0,
+                    processes,
+                    sched,
                 })
             },
         }
     };
 }
 
+/// Whether the kernel was built with `USER_TESTS` set, to run only the user
+/// test suite and report its results
+const USER_TESTS: bool = option_env!("USER_TESTS").is_some();
+
+/// Whether the app `name` belongs to the user test suite: its name starts
+/// with `test_` after the number, as in `14test_bad_fd`
+fn is_user_test(name: &str) -> bool {
+    name.trim_start_matches(|c: char| c.is_ascii_digit())
+        .starts_with("test_")
+}
+
+/// Whether app `name` passes with `exit_code`: `test_fault_*` tests pass
+/// when the kernel kills them, `test_hang_*` tests when it kills them for a
+/// deadlock, and every other app when it exits with 0
+fn user_test_passed(name: &str, exit_code: i32, kill_reason: Option<&str>) -> bool {
+    let name = name.trim_start_matches(|c: char| c.is_ascii_digit());
+    if name.starts_with("test_fault_") {
+        kill_reason.is_some()
+    } else if name.starts_with("test_hang_") {
+        kill_reason == Some(DEADLOCK)
+    } else {
+        kill_reason.is_none() && exit_code == 0
+    }
+}
+
+/// Kill reason of apps left blocked with nobody to wake them up
+const DEADLOCK: &str = "Deadlock";
+
+/// Id of the `Running` task, kept outside of `TASK_MANAGER` for code that
+/// must not borrow it such as the logger, or `usize::MAX` before the first
+/// task runs
+static RUNNING_TASK: AtomicUsize = AtomicUsize::new(usize::MAX);
+
 impl TaskManager {
     /// Run the first task in task list.
     ///
@@ -80,9 +136,16 @@ impl TaskManager {
     /// But in ch3, we load apps statically, so the first task is a real app.
     fn run_first_task(&self) -> ! {
         let mut inner = self.inner.exclusive_access();
-        let task0 = &mut inner.tasks[0];
-        task0.task_status = TaskStatus::Running;
-        let next_task_cx_ptr = &task0.task_cx as *const TaskContext;
+        let first = match inner.sched.start() {
+            Some(first) => first,
+            None => {
+                drop(inner);
+                println!("No application to run!");
+                shutdown(self.print_summary());
+            }
+        };
+        RUNNING_TASK.store(first, Ordering::Relaxed);
+        let next_task_cx_ptr = &inner.tasks[first].task_cx as *const TaskContext;
         drop(inner);
         let mut _unused = TaskContext::zero_init();
         // before this, we should drop local variables that must be dropped manually
@@ -94,49 +157,306 @@ impl TaskManager {
 
     /// Change the status of current `Running` task into `Ready`.
     fn mark_current_suspended(&self) {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        inner.tasks[current].task_status = TaskStatus::Ready;
+        self.inner.exclusive_access().sched.suspend_current();
+    }
+
+    /// Change the status of current `Running` task into `Blocked`.
+    fn mark_current_blocked(&self) {
+        self.inner.exclusive_access().sched.block_current();
     }
 
     /// Change the status of current `Running` task into `Exited`.
-    fn mark_current_exited(&self) {
+    ///
+    /// If it is the main thread, the whole process exits with it, see
+    /// [`Self::exit_process`]. Otherwise the deadlock tracker of the process
+    /// forgets it.
+    fn mark_current_exited(&self, exit_code: i32) {
         let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        inner.tasks[current].task_status = TaskStatus::Exited;
+        let current = inner.sched.current();
+        inner.sched.exit(current);
+        let task = &mut inner.tasks[current];
+        task.exit_code = Some(exit_code);
+        let (pid, tid) = (task.pid, task.tid);
+        if tid == 0 {
+            Self::exit_process(inner, pid);
+        } else {
+            inner.processes[pid].resources.clear_task(tid);
+        }
+    }
+
+    /// Kill the process of current `Running` task for `reason`, whichever
+    /// thread it is, with `exit_code` as the exit code of its main thread.
+    fn mark_current_killed(&self, exit_code: i32, reason: &'static str) {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.sched.current();
+        let pid = inner.tasks[current].pid;
+        inner.tasks[current].exit_code = Some(exit_code);
+        Self::kill_process(inner, pid, exit_code, reason);
+    }
+
+    /// Kill process `pid` for `reason`, with `exit_code` as the exit code of
+    /// its main thread.
+    fn kill_process(
+        mut inner: RefMut<'_, TaskManagerInner>,
+        pid: usize,
+        exit_code: i32,
+        reason: &'static str,
+    ) {
+        // the main thread of app i is task i
+        inner.tasks[pid].exit_code = Some(exit_code);
+        inner.processes[pid].kill_reason = Some(reason);
+        Self::exit_process(inner, pid);
     }
 
-    /// Find next task to run and return task id.
+    /// Mark all threads of process `pid` `Exited`, unmap its memory, and
+    /// drop its synchronization objects, deadlock tracker and files.
+    fn exit_process(mut inner: RefMut<'_, TaskManagerInner>, pid: usize) {
+        for task_id in 0..inner.tasks.len() {
+            if inner.tasks[task_id].pid == pid {
+                inner.sched.exit(task_id);
+            }
+        }
+        munmap_all(pid);
+        let process = &mut inner.processes[pid];
+        process.mutex_list.clear();
+        process.semaphore_list.clear();
+        process.condvar_list.clear();
+        process.resources = ResourceTracker::new();
+        let fd_table = core::mem::take(&mut process.fd_table);
+        // closing a file may wake up tasks, which needs the task manager
+        drop(inner);
+        drop(fd_table);
+    }
+
+    /// Change the status of a `Blocked` task into `Ready`.
     ///
-    /// In this case, we only return the first `Ready` task in task list.
-    fn find_next_task(&self) -> Option<usize> {
+    /// A task whose process has exited in the meantime stays `Exited`, and
+    /// `false` is returned.
+    fn wakeup_task(&self, task_id: usize) -> bool {
+        self.inner.exclusive_access().sched.wakeup(task_id)
+    }
+
+    /// Get the id of current `Running` task.
+    fn current_task_id(&self) -> usize {
+        self.inner.exclusive_access().sched.current()
+    }
+
+    /// Get the process id of current `Running` task.
+    fn current_pid(&self) -> usize {
         let inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        (current + 1..current + self.num_app + 1)
-            .map(|id| id % self.num_app)
-            .find(|id| inner.tasks[*id].task_status == TaskStatus::Ready)
+        inner.tasks[inner.sched.current()].pid
     }
 
-    /// Switch current `Running` task to the task we have found,
-    /// or there is no `Ready` task and we can exit with all applications completed
-    fn run_next_task(&self) {
-        if let Some(next) = self.find_next_task() {
-            let mut inner = self.inner.exclusive_access();
-            let current = inner.current_task;
-            inner.tasks[next].task_status = TaskStatus::Running;
-            inner.current_task = next;
-            let current_task_cx_ptr = &mut inner.tasks[current].task_cx as *mut TaskContext;
-            let next_task_cx_ptr = &inner.tasks[next].task_cx as *const TaskContext;
-            drop(inner);
-            // before this, we should drop local variables that must be dropped manually
+    /// Get the thread id of current `Running` task within its process.
+    fn current_tid(&self) -> usize {
+        let inner = self.inner.exclusive_access();
+        inner.tasks[inner.sched.current()].tid
+    }
+
+    /// Create a `Ready` thread in the current process, starting at `entry`
+    /// with `arg` in `a0`, and return its thread id.
+    fn create_thread(&self, entry: usize, arg: usize) -> usize {
+        let stack = ThreadStack::new();
+        let mut trap_cx = TrapContext::app_init_context(entry, stack.user_sp());
+        trap_cx.x[10] = arg;
+        let kstack_ptr = stack.push_context(trap_cx);
+        let mut inner = self.inner.exclusive_access();
+        let pid = inner.tasks[inner.sched.current()].pid;
+        let task_id = inner.tasks.len();
+        let tid = inner.processes[pid].threads.len();
+        inner.processes[pid].threads.push(Some(task_id));
+        assert_eq!(inner.sched.add_task(), task_id);
+        inner.tasks.push(TaskControlBlock {
+            pid,
+            tid,
+            task_cx: TaskContext::goto_restore(kstack_ptr),
+            stack: Some(stack),
+            exit_code: None,
+        });
+        tid
+    }
+
+    /// Get the exit code of thread `tid` of the current process and release
+    /// its stacks.
+    ///
+    /// Fail with -1 if there is no such thread or it is the current one, and
+    /// -2 if it has not exited yet.
+    fn waittid(&self, tid: usize) -> Result<i32, isize> {
+        let mut inner = self.inner.exclusive_access();
+        let current = &inner.tasks[inner.sched.current()];
+        if current.tid == tid {
+            return Err(-1);
+        }
+        let pid = current.pid;
+        let task_id = match inner.processes[pid].threads.get(tid) {
+            Some(Some(task_id)) => *task_id,
+            _ => return Err(-1),
+        };
+        if inner.sched.status(task_id) != TaskStatus::Exited {
+            return Err(-2);
+        }
+        let task = &mut inner.tasks[task_id];
+        let exit_code = task.exit_code.unwrap();
+        task.stack = None;
+        inner.processes[pid].threads[tid] = None;
+        Ok(exit_code)
+    }
+
+    /// Move the end of the heap of the current process by `increment` bytes
+    /// and return the old end, or `None` if it would leave the memory the
+    /// heap may take. Memory added to the heap is zeroed.
+    fn change_current_brk(&self, increment: isize) -> Option<usize> {
+        let mut inner = self.inner.exclusive_access();
+        let pid = inner.tasks[inner.sched.current()].pid;
+        let process = &mut inner.processes[pid];
+        let old_brk = process.brk;
+        let new_brk = old_brk.checked_add_signed(increment)?;
+        if !(process.heap.start..=process.heap.end).contains(&new_brk) {
+            return None;
+        }
+        if new_brk > old_brk {
             unsafe {
-                __switch(current_task_cx_ptr, next_task_cx_ptr);
+                core::ptr::write_bytes(old_brk as *mut u8, 0, new_brk - old_brk);
             }
-            // go back to user mode
-        } else {
-            println!("All applications completed!");
-            shutdown(false);
         }
+        process.brk = new_brk;
+        Some(old_brk)
+    }
+
+    /// Run `f` on the current process, which must neither block nor use the
+    /// task manager.
+    fn with_current_process<T>(&self, f: impl FnOnce(&mut ProcessControlBlock) -> T) -> T {
+        let mut inner = self.inner.exclusive_access();
+        let pid = inner.tasks[inner.sched.current()].pid;
+        f(&mut inner.processes[pid])
+    }
+
+    /// Get the file behind `fd` in the current process.
+    fn get_current_file(&self, fd: usize) -> Option<Arc<dyn File>> {
+        let inner = self.inner.exclusive_access();
+        let pid = inner.tasks[inner.sched.current()].pid;
+        inner.processes[pid].fd_table.get(fd).cloned().flatten()
+    }
+
+    /// Open `file` in the current process and return its file descriptor.
+    fn add_current_file(&self, file: Arc<dyn File>) -> usize {
+        let mut inner = self.inner.exclusive_access();
+        let pid = inner.tasks[inner.sched.current()].pid;
+        let process = &mut inner.processes[pid];
+        let fd = process.alloc_fd();
+        process.fd_table[fd] = Some(file);
+        fd
+    }
+
+    /// Remove `fd` from the current process and return the file behind it.
+    fn take_current_file(&self, fd: usize) -> Option<Arc<dyn File>> {
+        let mut inner = self.inner.exclusive_access();
+        let pid = inner.tasks[inner.sched.current()].pid;
+        inner.processes[pid]
+            .fd_table
+            .get_mut(fd)
+            .and_then(|file| file.take())
+    }
+
+    /// Switch current `Running` task to the next `Ready` one picked by the
+    /// scheduler, or there is no `Ready` task and we can exit with all
+    /// applications completed.
+    ///
+    /// If some tasks are still `Blocked` when nothing is `Ready`, nobody is
+    /// left to wake them up: their processes are killed for a deadlock, and
+    /// we exit as well.
+    fn run_next_task(&self) {
+        let mut inner = self.inner.exclusive_access();
+        match inner.sched.schedule() {
+            Schedule::Switch { from, to } => {
+                trace!("[kernel] switch from task {} to task {}", from, to);
+                RUNNING_TASK.store(to, Ordering::Relaxed);
+                let current_task_cx_ptr = &mut inner.tasks[from].task_cx as *mut TaskContext;
+                let next_task_cx_ptr = &inner.tasks[to].task_cx as *const TaskContext;
+                drop(inner);
+                // before this, we should drop local variables that must be dropped manually
+                unsafe {
+                    __switch(current_task_cx_ptr, next_task_cx_ptr);
+                }
+                // go back to user mode
+            }
+            Schedule::Deadlock => {
+                let mut hung: Vec<usize> = (0..inner.tasks.len())
+                    .filter(|task_id| inner.sched.status(*task_id) == TaskStatus::Blocked)
+                    .map(|task_id| inner.tasks[task_id].pid)
+                    .collect();
+                hung.sort_unstable();
+                hung.dedup();
+                drop(inner);
+                println!("All remaining applications are blocked, deadlock!");
+                for pid in hung {
+                    warn!(
+                        "[kernel] {} is blocked forever, kernel killed it.",
+                        get_app_name(pid)
+                    );
+                    Self::kill_process(self.inner.exclusive_access(), pid, -4, DEADLOCK);
+                }
+                shutdown(self.print_summary());
+            }
+            Schedule::AllExited => {
+                drop(inner);
+                println!("All applications completed!");
+                shutdown(self.print_summary());
+            }
+        }
+    }
+
+    /// Print how every app ended, and return whether any of them failed, as
+    /// [`user_test_passed`] judges it: the fault and hang tests are expected
+    /// to be killed, other apps to exit with 0. Apps that hung show as
+    /// killed for a deadlock.
+    ///
+    /// With `USER_TESTS`, only the results of the user tests count, and they
+    /// are tallied with a `[usertest]` line each.
+    fn print_summary(&self) -> bool {
+        let inner = self.inner.exclusive_access();
+        let mut failed = false;
+        let mut passed_tests = 0;
+        let mut failed_tests = 0;
+        println!("{:<4} {:<20} result", "app", "name");
+        for (pid, process) in inner.processes.iter().enumerate() {
+            let name = get_app_name(pid);
+            let exit_code = match inner.tasks[pid].exit_code {
+                Some(exit_code) => exit_code,
+                None => {
+                    println!("{:<4} {:<20} skipped", pid, name);
+                    continue;
+                }
+            };
+            if let Some(reason) = process.kill_reason {
+                println!(
+                    "{:<4} {:<20} killed: {}, code {}",
+                    pid, name, reason, exit_code
+                );
+            } else {
+                println!("{:<4} {:<20} exited with code {}", pid, name, exit_code);
+            }
+            let passed = user_test_passed(name, exit_code, process.kill_reason);
+            if !USER_TESTS {
+                failed |= !passed;
+            } else if passed {
+                println!("[usertest] {} ... ok", name);
+                passed_tests += 1;
+            } else {
+                println!("[usertest] {} ... FAILED", name);
+                failed_tests += 1;
+            }
+        }
+        if USER_TESTS {
+            failed = failed_tests > 0;
+            println!(
+                "[usertest] result: {}. {} passed; {} failed",
+                if failed { "FAILED" } else { "ok" },
+                passed_tests,
+                failed_tests
+            );
+        }
+        failed
     }
 }
 
@@ -156,8 +476,13 @@ fn mark_current_suspended() {
 }
 
 /// exit current task
-fn mark_current_exited() {
-    TASK_MANAGER.mark_current_exited();
+fn mark_current_exited(exit_code: i32) {
+    TASK_MANAGER.mark_current_exited(exit_code);
+}
+
+/// block current task
+fn mark_current_blocked() {
+    TASK_MANAGER.mark_current_blocked();
 }
 
 /// suspend current task, then run next task
@@ -166,8 +491,120 @@ pub fn suspend_current_and_run_next() {
     run_next_task();
 }
 
+/// block current task, then run next task
+///
+/// The caller is responsible for putting the current task into a wait queue
+/// from which it will later be passed to [`wakeup_task()`].
+pub fn block_current_and_run_next() {
+    mark_current_blocked();
+    run_next_task();
+}
+
+/// wake up a blocked task so that it can be scheduled again, returning
+/// `false` if it has exited instead
+pub fn wakeup_task(task_id: usize) -> bool {
+    TASK_MANAGER.wakeup_task(task_id)
+}
+
+/// get the id of current task
+pub fn current_task_id() -> usize {
+    TASK_MANAGER.current_task_id()
+}
+
+/// get the id of the running task without borrowing `TASK_MANAGER`, which
+/// is `None` before the first task runs
+pub fn running_task_id() -> Option<usize> {
+    match RUNNING_TASK.load(Ordering::Relaxed) {
+        usize::MAX => None,
+        task_id => Some(task_id),
+    }
+}
+
+/// get the process id of current task
+pub fn current_pid() -> usize {
+    TASK_MANAGER.current_pid()
+}
+
+/// get the thread id of current task within its process
+pub fn current_tid() -> usize {
+    TASK_MANAGER.current_tid()
+}
+
+/// move the end of the heap of the current process, see [`TaskManager::change_current_brk`]
+pub fn change_current_brk(increment: isize) -> Option<usize> {
+    TASK_MANAGER.change_current_brk(increment)
+}
+
+/// run `f` on the current process, see [`TaskManager::with_current_process`]
+pub fn with_current_process<T>(f: impl FnOnce(&mut ProcessControlBlock) -> T) -> T {
+    TASK_MANAGER.with_current_process(f)
+}
+
+/// get the file behind `fd` in the current process
+pub fn get_current_file(fd: usize) -> Option<Arc<dyn File>> {
+    TASK_MANAGER.get_current_file(fd)
+}
+
+/// open `file` in the current process and return its file descriptor
+pub fn add_current_file(file: Arc<dyn File>) -> usize {
+    TASK_MANAGER.add_current_file(file)
+}
+
+/// remove `fd` from the current process and return the file behind it
+pub fn take_current_file(fd: usize) -> Option<Arc<dyn File>> {
+    TASK_MANAGER.take_current_file(fd)
+}
+
+/// create a thread in the current process and return its thread id
+pub fn create_thread(entry: usize, arg: usize) -> usize {
+    TASK_MANAGER.create_thread(entry, arg)
+}
+
+/// get the exit code of a thread of the current process, see [`TaskManager::waittid`]
+pub fn waittid(tid: usize) -> Result<i32, isize> {
+    TASK_MANAGER.waittid(tid)
+}
+
 /// exit current task,  then run next task
-pub fn exit_current_and_run_next() {
-    mark_current_exited();
+pub fn exit_current_and_run_next(exit_code: i32) {
+    mark_current_exited(exit_code);
+    run_next_task();
+}
+
+/// kill the process of current task for `reason`, then run next task
+pub fn kill_current_and_run_next(exit_code: i32, reason: &'static str) {
+    TASK_MANAGER.mark_current_killed(exit_code, reason);
     run_next_task();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn apps_start_as_ready_main_threads() {
+        // tests run before the first task
+        assert_eq!(running_task_id(), None);
+        let inner = TASK_MANAGER.inner.exclusive_access();
+        assert_eq!(inner.tasks.len(), get_num_app());
+        assert_eq!(inner.processes.len(), get_num_app());
+        assert_eq!(inner.sched.len(), get_num_app());
+        for (task_id, task) in inner.tasks.iter().enumerate() {
+            assert_eq!(task.pid, task_id);
+            assert_eq!(task.tid, 0);
+            assert_eq!(inner.sched.status(task_id), TaskStatus::Ready);
+        }
+    }
+
+    #[test_case]
+    fn user_tests_pass_by_how_they_end() {
+        let fault = Some("PageFault");
+        assert!(user_test_passed("15test_get_time", 0, None));
+        assert!(!user_test_passed("15test_get_time", 1, None));
+        assert!(user_test_passed("18test_fault_store", -2, fault));
+        assert!(!user_test_passed("18test_fault_store", 0, None));
+        assert!(user_test_passed("26test_hang_mutex", -4, Some(DEADLOCK)));
+        assert!(!user_test_passed("26test_hang_mutex", -2, fault));
+        assert!(!user_test_passed("26test_hang_mutex", 0, None));
+    }
+}
@@ -1,17 +1,83 @@
 //! Types related to task management
 
 use super::TaskContext;
+use crate::fs::{File, Stdin, Stdout};
+use crate::loader::{get_app_heap, ThreadStack};
+use crate::sync::{Condvar, Mutex, ResourceTracker, Semaphore};
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::ops::Range;
 
-#[derive(Copy, Clone)]
+/// A thread: the unit of scheduling
 pub struct TaskControlBlock {
-    pub task_status: TaskStatus,
+    /// index of the process (app) this thread belongs to
+    pub pid: usize,
+    /// thread id within the process, the main thread being 0
+    pub tid: usize,
     pub task_cx: TaskContext,
+    /// stacks allocated for a thread created at runtime, released when the
+    /// thread has been waited for; main threads use the stacks of their app
+    pub stack: Option<ThreadStack>,
+    /// exit code, once the thread has exited
+    pub exit_code: Option<i32>,
 }
 
-#[derive(Copy, Clone, PartialEq)]
-pub enum TaskStatus {
-    UnInit,
-    Ready,
-    Running,
-    Exited,
+/// A process: an app with the threads sharing its memory
+pub struct ProcessControlBlock {
+    /// task ids of the threads indexed by tid, `None` once waited for
+    pub threads: Vec<Option<usize>>,
+    /// whether lock and down requests leading to an unsafe state are refused
+    pub deadlock_detect: bool,
+    /// available, allocation and need of its mutexes and semaphores, by tid
+    pub resources: ResourceTracker,
+    /// open files indexed by file descriptor
+    pub fd_table: Vec<Option<Arc<dyn File>>>,
+    /// why the kernel killed the process, if it did
+    pub kill_reason: Option<&'static str>,
+    /// memory the heap may take, after the `.bss` of the app
+    pub heap: Range<usize>,
+    /// end of the heap, moved by `sys_sbrk`
+    pub brk: usize,
+    /// mutexes indexed by id, with their deadlock detection resource id
+    pub mutex_list: Vec<(Arc<dyn Mutex>, usize)>,
+    /// semaphores indexed by id, with their deadlock detection resource id
+    pub semaphore_list: Vec<(Arc<Semaphore>, usize)>,
+    /// condition variables indexed by id
+    pub condvar_list: Vec<Arc<Condvar>>,
+}
+
+impl ProcessControlBlock {
+    /// Create a process whose main thread is task `task_id`, with stdin,
+    /// stdout and stderr open
+    pub fn new(task_id: usize) -> Self {
+        let heap = get_app_heap(task_id);
+        Self {
+            threads: vec![Some(task_id)],
+            deadlock_detect: false,
+            resources: ResourceTracker::new(),
+            fd_table: vec![
+                // 0 -> stdin
+                Some(Arc::new(Stdin)),
+                // 1 -> stdout
+                Some(Arc::new(Stdout)),
+                // 2 -> stderr
+                Some(Arc::new(Stdout)),
+            ],
+            kill_reason: None,
+            brk: heap.start,
+            heap,
+            mutex_list: Vec::new(),
+            semaphore_list: Vec::new(),
+            condvar_list: Vec::new(),
+        }
+    }
+
+    /// Find the lowest free file descriptor
+    pub fn alloc_fd(&mut self) -> usize {
+        if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
+            fd
+        } else {
+            self.fd_table.push(None);
+            self.fd_table.len() - 1
+        }
+    }
 }
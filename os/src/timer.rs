@@ -6,6 +6,7 @@ use riscv::register::time;
 
 const TICKS_PER_SEC: usize = 100;
 const MSEC_PER_SEC: usize = 1000;
+const USEC_PER_SEC: usize = 1_000_000;
 
 /// read the `mtime` register
 pub fn get_time() -> usize {
@@ -17,7 +18,32 @@ pub fn get_time_ms() -> usize {
     time::read() / (CLOCK_FREQ / MSEC_PER_SEC)
 }
 
+/// get current time in microseconds
+pub fn get_time_us() -> usize {
+    time::read() * USEC_PER_SEC / CLOCK_FREQ
+}
+
 /// set the next timer interrupt
 pub fn set_next_trigger() {
     set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn time_goes_forward() {
+        let start = get_time();
+        while get_time() == start {}
+        assert!(get_time() > start);
+    }
+
+    #[test_case]
+    fn time_units_agree() {
+        let us = get_time_us();
+        let ms = get_time_ms();
+        // the microseconds were read first, and less than a second passed
+        assert!(us / 1000 <= ms && ms <= us / 1000 + MSEC_PER_SEC);
+    }
+}
@@ -1,6 +1,16 @@
-use riscv::register::sstatus::{self, Sstatus, SPP};
+use riscv::register::sstatus::{self, Sstatus, FS, SPP};
 /// Trap Context
-#[repr(C)]
+///
+/// The floating-point registers are switched lazily. A task starts with
+/// `sstatus.FS` Off, so that integer-only tasks never have them saved or
+/// restored; its first floating-point instruction traps and turns them on
+/// (Initial). They are then saved only when `sstatus.FS` says the task has
+/// written them since they were last saved, and kept here across traps, as
+/// every trap from a task puts its context at the same place on its kernel
+/// stack. When returning to a task using them, they are restored, or zeroed
+/// while it has not written them yet, only if another task's are live in
+/// the hart, which [`FP_OWNER`](super::FP_OWNER) tells.
+#[repr(C, align(16))]
 pub struct TrapContext {
     /// general regs[0..31]
     pub x: [usize; 32],
@@ -8,6 +18,10 @@ pub struct TrapContext {
     pub sstatus: Sstatus,
     /// CSR sepc
     pub sepc: usize,
+    /// floating-point regs[0..31]
+    pub f: [usize; 32],
+    /// CSR fcsr
+    pub fcsr: usize,
 }
 
 impl TrapContext {
@@ -17,12 +31,18 @@ impl TrapContext {
     }
     /// init app context
     pub fn app_init_context(entry: usize, sp: usize) -> Self {
+        // FP registers off until the task uses them
+        unsafe {
+            sstatus::set_fs(FS::Off);
+        }
         let mut sstatus = sstatus::read(); // CSR sstatus
         sstatus.set_spp(SPP::User); //previous privilege mode: user mode
         let mut cx = Self {
             x: [0; 32],
             sstatus,
             sepc: entry, // entry point of app
+            f: [0; 32],
+            fcsr: 0,
         };
         cx.set_sp(sp); // app's user stack pointer
         cx // return initial Trap Context of app
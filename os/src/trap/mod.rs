@@ -15,13 +15,17 @@
 mod context;
 
 use crate::syscall::syscall;
-use crate::task::{exit_current_and_run_next, suspend_current_and_run_next};
+use crate::task::{kill_current_and_run_next, running_task_id, suspend_current_and_run_next};
 use crate::timer::set_next_trigger;
 use core::arch::global_asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use log::*;
 use riscv::register::{
     mtvec::TrapMode,
     scause::{self, Exception, Interrupt, Trap},
-    sie, stval, stvec,
+    sie,
+    sstatus::{self, FS},
+    stval, stvec,
 };
 
 global_asm!(include_str!("trap.S"));
@@ -43,23 +47,76 @@ pub fn enable_timer_interrupt() {
     }
 }
 
+/// Address of the `TrapContext` of the task whose floating-point registers
+/// are live in the hart, or 0 if none, kept by `__restore`. The kernel
+/// itself never touches them.
+#[no_mangle]
+static FP_OWNER: AtomicUsize = AtomicUsize::new(0);
+
+/// Address of the `TrapContext` of the last trap, or 0 before any or once
+/// the stack holding it is freed
+static LAST_TRAP_CX: AtomicUsize = AtomicUsize::new(0);
+/// Id of the task the last trap came from
+static LAST_TRAP_TASK: AtomicUsize = AtomicUsize::new(0);
+
+/// The id of the task the last trap came from and its `TrapContext`, as
+/// saved on entry, for the panic handler
+pub fn last_trap_context() -> Option<(usize, &'static TrapContext)> {
+    match LAST_TRAP_CX.load(Ordering::Relaxed) {
+        0 => None,
+        cx => Some((LAST_TRAP_TASK.load(Ordering::Relaxed), unsafe {
+            &*(cx as *const TrapContext)
+        })),
+    }
+}
+
+/// Forget the last trap if its `TrapContext` is on `kernel_stack`, which
+/// is being freed
+pub fn forget_trap_context_on(kernel_stack: &[u8]) {
+    let stack = kernel_stack.as_ptr_range();
+    let cx = LAST_TRAP_CX.load(Ordering::Relaxed);
+    if (stack.start as usize..stack.end as usize).contains(&cx) {
+        LAST_TRAP_CX.store(0, Ordering::Relaxed);
+    }
+}
+
 #[no_mangle]
 /// handle an interrupt, exception, or system call from user space
 pub fn trap_handler(cx: &mut TrapContext) -> &mut TrapContext {
     let scause = scause::read(); // get trap cause
     let stval = stval::read(); // get extra value
+    LAST_TRAP_CX.store(cx as *mut TrapContext as usize, Ordering::Relaxed);
+    LAST_TRAP_TASK.store(running_task_id().unwrap_or(0), Ordering::Relaxed);
     match scause.cause() {
         Trap::Exception(Exception::UserEnvCall) => {
             cx.sepc += 4;
-            cx.x[10] = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]) as usize;
+            trace!("[kernel] syscall {}", cx.x[17]);
+            let args = [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14], cx.x[15]];
+            cx.x[10] = syscall(cx.x[17], args) as usize;
+        }
+        // A fault of any kind only ends the app that caused it
+        Trap::Exception(Exception::StoreFault)
+        | Trap::Exception(Exception::StorePageFault)
+        | Trap::Exception(Exception::LoadFault)
+        | Trap::Exception(Exception::LoadPageFault)
+        | Trap::Exception(Exception::InstructionFault)
+        | Trap::Exception(Exception::InstructionPageFault) => {
+            warn!("[kernel] PageFault in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.", stval, cx.sepc);
+            kill_current_and_run_next(-2, "PageFault");
         }
-        Trap::Exception(Exception::StoreFault) | Trap::Exception(Exception::StorePageFault) => {
-            println!("[kernel] PageFault in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.", stval, cx.sepc);
-            exit_current_and_run_next();
+        // The first floating-point instruction of a task turns them on and
+        // runs again. Whoever owned the context address before it may have
+        // left its registers live, so they are zeroed for it anyway.
+        Trap::Exception(Exception::IllegalInstruction) if cx.sstatus.fs() == FS::Off => {
+            unsafe {
+                sstatus::set_fs(FS::Initial);
+            }
+            cx.sstatus = sstatus::read();
+            FP_OWNER.store(0, Ordering::Relaxed);
         }
         Trap::Exception(Exception::IllegalInstruction) => {
-            println!("[kernel] IllegalInstruction in application, kernel killed it.");
-            exit_current_and_run_next();
+            warn!("[kernel] IllegalInstruction in application, kernel killed it.");
+            kill_current_and_run_next(-3, "IllegalInstruction");
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
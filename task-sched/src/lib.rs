@@ -0,0 +1,146 @@
+//! Task scheduling state machine isolated from the kernel
+//!
+//! [`Scheduler`] follows the status of every task and which one is running,
+//! and picks the next task round-robin. It does not switch contexts: the
+//! kernel asks it for a [`Schedule`] decision and then calls `__switch`
+//! itself, so the same logic builds and is tested on the host.
+//!
+//! Tasks are named by their index, and are never removed.
+#![no_std]
+#![deny(missing_docs)]
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+/// Status of a task
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TaskStatus {
+    /// waiting to be picked
+    Ready,
+    /// the current task, on the CPU
+    Running,
+    /// waiting to be woken up
+    Blocked,
+    /// never runs again
+    Exited,
+}
+
+/// What to do once the current task stopped running
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Schedule {
+    /// switch from the task `from` to the task `to`, which may be the same
+    Switch {
+        /// the task that was current
+        from: usize,
+        /// the task that is now `Running`
+        to: usize,
+    },
+    /// nothing is `Ready`, but some tasks are `Blocked` with nobody left to
+    /// wake them up
+    Deadlock,
+    /// every task has exited
+    AllExited,
+}
+
+/// Status of all tasks, and the current one
+pub struct Scheduler {
+    status: Vec<TaskStatus>,
+    current: usize,
+}
+
+impl Scheduler {
+    /// A scheduler of `num_task` `Ready` tasks, task 0 being current
+    pub fn new(num_task: usize) -> Self {
+        let mut status = Vec::new();
+        status.resize(num_task, TaskStatus::Ready);
+        Self { status, current: 0 }
+    }
+
+    /// Number of tasks
+    pub fn len(&self) -> usize {
+        self.status.len()
+    }
+
+    /// Whether there is no task
+    pub fn is_empty(&self) -> bool {
+        self.status.is_empty()
+    }
+
+    /// Id of the current task, the one `Running` unless it just stopped
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Status of task `task`
+    pub fn status(&self, task: usize) -> TaskStatus {
+        self.status[task]
+    }
+
+    /// Add a `Ready` task and return its id
+    pub fn add_task(&mut self) -> usize {
+        self.status.push(TaskStatus::Ready);
+        self.status.len() - 1
+    }
+
+    /// Run the first `Ready` task, if any, and return its id
+    pub fn start(&mut self) -> Option<usize> {
+        let first = self.status.iter().position(|s| *s == TaskStatus::Ready)?;
+        self.current = first;
+        self.status[first] = TaskStatus::Running;
+        Some(first)
+    }
+
+    /// Change the status of the current task into `Ready`
+    pub fn suspend_current(&mut self) {
+        self.status[self.current] = TaskStatus::Ready;
+    }
+
+    /// Change the status of the current task into `Blocked`
+    pub fn block_current(&mut self) {
+        self.status[self.current] = TaskStatus::Blocked;
+    }
+
+    /// Change the status of task `task`, current or not, into `Exited`
+    pub fn exit(&mut self, task: usize) {
+        self.status[task] = TaskStatus::Exited;
+    }
+
+    /// Change the status of task `task` into `Ready` if it is `Blocked`.
+    /// A task that exited in the meantime stays `Exited`, and `false` is
+    /// returned so that what it waited for can go to another task.
+    pub fn wakeup(&mut self, task: usize) -> bool {
+        if self.status[task] == TaskStatus::Blocked {
+            self.status[task] = TaskStatus::Ready;
+        }
+        self.status[task] != TaskStatus::Exited
+    }
+
+    /// Whether any task is waiting to be woken up
+    pub fn has_blocked(&self) -> bool {
+        self.status.contains(&TaskStatus::Blocked)
+    }
+
+    /// Find the first `Ready` task after the current one, wrapping around
+    /// to the current task itself last
+    pub fn find_next(&self) -> Option<usize> {
+        let num_task = self.status.len();
+        (self.current + 1..self.current + num_task + 1)
+            .map(|id| id % num_task)
+            .find(|id| self.status[*id] == TaskStatus::Ready)
+    }
+
+    /// Pick the next task once the current one stopped running, and make
+    /// it the current `Running` task
+    pub fn schedule(&mut self) -> Schedule {
+        match self.find_next() {
+            Some(next) => {
+                let from = self.current;
+                self.status[next] = TaskStatus::Running;
+                self.current = next;
+                Schedule::Switch { from, to: next }
+            }
+            None if self.has_blocked() => Schedule::Deadlock,
+            None => Schedule::AllExited,
+        }
+    }
+}
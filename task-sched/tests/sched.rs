@@ -0,0 +1,143 @@
+//! Property tests of the scheduler under random sequences of operations,
+//! the way the kernel drives it
+use proptest::prelude::*;
+use task_sched::{Schedule, Scheduler, TaskStatus};
+
+#[derive(Clone, Debug)]
+enum Op {
+    /// the current task yields, or a timer tick preempts it
+    Yield,
+    /// the current task blocks
+    Block,
+    /// the current task exits
+    Exit,
+    /// the current task kills another task, as when a process exits
+    Kill(usize),
+    /// the current task wakes up another task
+    Wakeup(usize),
+    /// the current task creates a task
+    Spawn,
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => Just(Op::Yield),
+        2 => Just(Op::Block),
+        1 => Just(Op::Exit),
+        1 => any::<usize>().prop_map(Op::Kill),
+        3 => any::<usize>().prop_map(Op::Wakeup),
+        1 => Just(Op::Spawn),
+    ]
+}
+
+/// Check the state of a scheduler between operations, with `current`
+/// running
+fn check_running(sched: &Scheduler) {
+    let running: Vec<usize> = (0..sched.len())
+        .filter(|task| sched.status(*task) == TaskStatus::Running)
+        .collect();
+    assert_eq!(running, vec![sched.current()]);
+}
+
+/// Apply `op` and return the decision taken, if the current task stopped
+fn apply(sched: &mut Scheduler, op: &Op) -> Option<Schedule> {
+    let current = sched.current();
+    match *op {
+        Op::Yield => sched.suspend_current(),
+        Op::Block => sched.block_current(),
+        Op::Exit => sched.exit(current),
+        Op::Kill(task) => {
+            let task = task % sched.len();
+            sched.exit(task);
+            if task != current {
+                return None;
+            }
+        }
+        Op::Wakeup(task) => {
+            let task = task % sched.len();
+            let alive = sched.wakeup(task);
+            assert_eq!(alive, sched.status(task) != TaskStatus::Exited);
+            return None;
+        }
+        Op::Spawn => {
+            sched.add_task();
+            return None;
+        }
+    }
+    Some(sched.schedule())
+}
+
+proptest! {
+    #[test]
+    fn exactly_one_task_runs(num_task in 1..8usize, ops in prop::collection::vec(op(), 0..200)) {
+        let mut sched = Scheduler::new(num_task);
+        assert_eq!(sched.start(), Some(0));
+        for op in &ops {
+            match apply(&mut sched, op) {
+                None | Some(Schedule::Switch { .. }) => check_running(&sched),
+                Some(_) => break,
+            }
+        }
+    }
+
+    #[test]
+    fn exited_tasks_never_run_again(num_task in 1..8usize, ops in prop::collection::vec(op(), 0..200)) {
+        let mut sched = Scheduler::new(num_task);
+        assert_eq!(sched.start(), Some(0));
+        let mut exited = vec![false; num_task];
+        for op in &ops {
+            let decision = apply(&mut sched, op);
+            exited.resize(sched.len(), false);
+            if let Some(Schedule::Switch { to, .. }) = decision {
+                prop_assert!(!exited[to]);
+            }
+            for (task, exited) in exited.iter_mut().enumerate() {
+                if *exited {
+                    prop_assert_eq!(sched.status(task), TaskStatus::Exited);
+                }
+                *exited = sched.status(task) == TaskStatus::Exited;
+            }
+            if matches!(decision, Some(Schedule::Deadlock | Schedule::AllExited)) {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn ready_tasks_run_within_a_round(num_task in 1..8usize, ops in prop::collection::vec(op(), 0..200)) {
+        let mut sched = Scheduler::new(num_task);
+        assert_eq!(sched.start(), Some(0));
+        // switches each task has been passed over while `Ready`
+        let mut passed_over = vec![0; num_task];
+        for op in &ops {
+            let ready: Vec<bool> = (0..sched.len())
+                .map(|task| sched.status(task) == TaskStatus::Ready)
+                .collect();
+            let decision = apply(&mut sched, op);
+            passed_over.resize(sched.len(), 0);
+            match decision {
+                Some(Schedule::Switch { to, .. }) => {
+                    for (task, was_ready) in ready.iter().enumerate() {
+                        if task == to || !was_ready {
+                            passed_over[task] = 0;
+                        } else {
+                            passed_over[task] += 1;
+                            prop_assert!(passed_over[task] < sched.len());
+                        }
+                    }
+                    passed_over[to] = 0;
+                }
+                Some(Schedule::Deadlock) => {
+                    prop_assert!(sched.has_blocked());
+                    prop_assert!(sched.find_next().is_none());
+                    break;
+                }
+                Some(Schedule::AllExited) => {
+                    prop_assert!((0..sched.len()).all(|task| sched.status(task) == TaskStatus::Exited));
+                    break;
+                }
+                None => {}
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
 import os
 
-base_address = 0x80400000
+base_address = 0x80800000
 step = 0x20000
 linker = 'src/linker.ld'
 
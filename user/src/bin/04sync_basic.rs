@@ -0,0 +1,28 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::sync::{Condvar, Mutex, Semaphore};
+use user_lib::{mutex_lock, semaphore_up};
+
+user_lib::kernel_only!();
+
+#[no_mangle]
+fn main() -> i32 {
+    let mutex = Mutex::new_blocking();
+    {
+        let _guard = mutex.lock();
+    }
+    let sem = Semaphore::new(1);
+    sem.down();
+    sem.up();
+    let condvar = Condvar::new();
+    // nobody is waiting, the signal is simply lost
+    condvar.signal();
+    assert_eq!(mutex_lock(usize::MAX), -1);
+    assert_eq!(semaphore_up(usize::MAX), -1);
+    println!("Test sync_basic OK!");
+    0
+}
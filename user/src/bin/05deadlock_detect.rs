@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::sync::{Mutex, Semaphore};
+use user_lib::{enable_deadlock_detect, EDEADLK};
+
+user_lib::kernel_only!();
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(enable_deadlock_detect(true), 0);
+    let mutex = Mutex::new_blocking();
+    let guard = mutex.lock();
+    // locking it again would block this task forever
+    assert_eq!(mutex.lock_checked().err(), Some(EDEADLK));
+    drop(guard);
+    let sem = Semaphore::new(1);
+    sem.down();
+    assert_eq!(sem.down_checked(), Err(EDEADLK));
+    sem.up();
+    // nobody holds a resource of an empty semaphore to give back
+    let empty = Semaphore::new(0);
+    assert_eq!(empty.down_checked(), Err(EDEADLK));
+    empty.up();
+    empty.down();
+    println!("Test deadlock_detect OK!");
+    0
+}
@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, gettid, thread_create, waittid};
+
+user_lib::kernel_only!();
+
+fn thread_a(arg: usize) -> ! {
+    for _ in 0..1000 {
+        print!("a");
+    }
+    println!("");
+    exit(arg as i32);
+    unreachable!()
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(gettid(), 0);
+    let tids = [
+        thread_create(thread_a as usize, 1),
+        thread_create(thread_a as usize, 2),
+        thread_create(thread_a as usize, 3),
+    ];
+    for (i, tid) in tids.iter().enumerate() {
+        let exit_code = waittid(*tid as usize).unwrap();
+        println!("thread#{} exited with code {}", tid, exit_code);
+        assert_eq!(exit_code, i as i32 + 1);
+    }
+    assert_eq!(waittid(0), None);
+    println!("Test threads OK!");
+    0
+}
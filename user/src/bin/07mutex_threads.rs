@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::sync::Mutex;
+use user_lib::{exit, thread_create, waittid};
+
+user_lib::kernel_only!();
+
+const PER_THREAD: usize = 1000;
+const THREAD_COUNT: usize = 8;
+
+static mut A: usize = 0;
+
+fn adder(mutex_id: usize) -> ! {
+    let mutex = Mutex::from_id(mutex_id);
+    for _ in 0..PER_THREAD {
+        let _guard = mutex.lock();
+        unsafe {
+            // a non-atomic read-modify-write, only safe under the lock
+            let a = core::ptr::addr_of_mut!(A);
+            let t = a.read_volatile();
+            for _ in 0..100 {
+                core::hint::spin_loop();
+            }
+            a.write_volatile(t + 1);
+        }
+    }
+    exit(0);
+    unreachable!()
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mutex = Mutex::new_blocking();
+    let mut tids = [0; THREAD_COUNT];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(adder as usize, mutex.id()) as usize;
+    }
+    for tid in tids.iter() {
+        assert_eq!(waittid(*tid), Some(0));
+    }
+    let a = unsafe { core::ptr::addr_of!(A).read_volatile() };
+    assert_eq!(a, PER_THREAD * THREAD_COUNT);
+    println!("Test mutex_threads OK!");
+    0
+}
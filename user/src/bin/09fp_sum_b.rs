@@ -0,0 +1,26 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::hint::black_box;
+
+const ITER: usize = 3_000_000;
+
+#[no_mangle]
+fn main() -> i32 {
+    // every partial sum is a multiple of 0.25 small enough to be exact, so
+    // any corruption of the FP registers by another app shows up
+    let step = black_box(0.25f64);
+    let mut acc = 0.0f64;
+    let mut expected = 0usize;
+    for i in 0..ITER {
+        acc += (i % 11) as f64 * step;
+        expected += i % 11;
+    }
+    println!("fp_sum_b: {} (expected {})", acc, expected as f64 * 0.25);
+    assert_eq!(acc, expected as f64 * 0.25);
+    println!("Test fp_sum_b OK!");
+    0
+}
@@ -0,0 +1,81 @@
+#![no_std]
+#![no_main]
+#![feature(thread_local)]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use user_lib::{exit, thread_create, waittid, yield_};
+
+user_lib::kernel_only!();
+
+#[thread_local]
+static mut COUNTER: usize = 100;
+#[thread_local]
+static mut ZEROED: [usize; 4] = [0; 4];
+
+fn bump(times: usize) -> usize {
+    unsafe {
+        for _ in 0..times {
+            COUNTER += 1;
+            ZEROED[times % 4] += 1;
+        }
+        COUNTER + ZEROED[times % 4]
+    }
+}
+
+/// Lets the threads of `fill_pool` exit
+static RELEASE: AtomicBool = AtomicBool::new(false);
+
+fn waiter(_arg: usize) -> ! {
+    while !RELEASE.load(Ordering::Acquire) {
+        yield_();
+    }
+    exit(0);
+    unreachable!()
+}
+
+/// Create threads until the thread-local storage of the app runs out, which
+/// makes `thread_create` fail instead of panicking, and return how many
+/// could be created
+fn fill_pool() -> usize {
+    let mut tids = [0isize; 32];
+    let mut count = 0;
+    loop {
+        let tid = thread_create(waiter as usize, 0);
+        if tid < 0 {
+            break;
+        }
+        tids[count] = tid;
+        count += 1;
+    }
+    RELEASE.store(true, Ordering::Release);
+    for tid in &tids[..count] {
+        assert_eq!(waittid(*tid as usize), Some(0));
+    }
+    count
+}
+
+fn worker(times: usize) -> ! {
+    // every thread starts from the initial values, whatever the others do
+    exit(bump(times) as i32);
+    unreachable!()
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let tids = [
+        thread_create(worker as usize, 1000),
+        thread_create(worker as usize, 2000),
+    ];
+    assert_eq!(bump(10), 120);
+    for (tid, times) in tids.iter().zip([1000, 2000]) {
+        assert_eq!(waittid(*tid as usize), Some(100 + 2 * times));
+    }
+    assert_eq!(bump(10), 140);
+    // 16 blocks, one of which the main thread keeps
+    assert_eq!(fill_pool(), 15);
+    println!("Test tls OK!");
+    0
+}
@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, dup, exit, pipe, read, thread_create, waittid, write};
+
+user_lib::kernel_only!();
+
+const LEN: usize = 1000;
+
+/// Stream LEN bytes into the pipe, much more than it can buffer at once
+fn producer(write_fd: usize) -> ! {
+    let mut sent = 0;
+    while sent < LEN {
+        let chunk: [u8; 64] = core::array::from_fn(|i| ((sent + i) % 251) as u8);
+        let len = chunk.len().min(LEN - sent);
+        assert_eq!(write(write_fd, &chunk[..len]), len as isize);
+        sent += len;
+    }
+    // the reader sees the end of the stream once the last write end is closed
+    assert_eq!(close(write_fd), 0);
+    exit(0);
+    unreachable!()
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(write(pipe_fd[0], b"x"), -1);
+    // a second write end keeps the pipe open until it is closed too
+    let write_fd2 = dup(pipe_fd[1]) as usize;
+    assert_eq!(close(write_fd2), 0);
+    assert_eq!(close(write_fd2), -1);
+    let tid = thread_create(producer as usize, pipe_fd[1]);
+    let mut buf = [0u8; 100];
+    let mut received = 0;
+    loop {
+        match read(pipe_fd[0], &mut buf) {
+            0 => break,
+            len if len > 0 => {
+                for (i, byte) in buf[..len as usize].iter().enumerate() {
+                    assert_eq!(*byte, ((received + i) % 251) as u8);
+                }
+                received += len as usize;
+            }
+            _ => panic!("read from pipe failed"),
+        }
+    }
+    assert_eq!(received, LEN);
+    assert_eq!(waittid(tid as usize), Some(0));
+    assert_eq!(close(pipe_fd[0]), 0);
+    println!("Test pipe OK!");
+    0
+}
@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, fstat, link, open, read, unlink, write, OpenFlags, Stat, StatMode};
+
+user_lib::kernel_only!();
+
+#[no_mangle]
+fn main() -> i32 {
+    let test_str = "Hello, world!";
+    let filea = "filea\0";
+    let fd = open(filea, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, test_str.as_bytes()), test_str.len() as isize);
+    assert_eq!(read(fd, &mut [0u8; 1]), -1);
+    close(fd);
+
+    let fd = open(filea, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let mut buffer = [0u8; 100];
+    let read_len = read(fd, &mut buffer) as usize;
+    assert_eq!(test_str, core::str::from_utf8(&buffer[..read_len]).unwrap());
+    assert_eq!(read(fd, &mut buffer), 0);
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert_eq!(stat.mode, StatMode::FILE);
+    assert_eq!(stat.nlink, 1);
+    close(fd);
+
+    // a hard link shares the inode
+    assert_eq!(link(filea, "fileb\0"), 0);
+    assert_eq!(link(filea, "fileb\0"), -1);
+    let fd = open("fileb\0", OpenFlags::RDONLY) as usize;
+    let mut stat_b = Stat::new();
+    assert_eq!(fstat(fd, &mut stat_b), 0);
+    assert_eq!(stat_b.ino, stat.ino);
+    assert_eq!(stat_b.nlink, 2);
+    close(fd);
+    assert_eq!(unlink(filea), 0);
+    assert_eq!(open(filea, OpenFlags::RDONLY), -1);
+    let fd = open("fileb\0", OpenFlags::RDWR | OpenFlags::TRUNC) as usize;
+    assert_eq!(fstat(fd, &mut stat_b), 0);
+    assert_eq!(stat_b.nlink, 1);
+    assert_eq!(read(fd, &mut buffer), 0);
+    assert_eq!(write(fd, test_str.as_bytes()), test_str.len() as isize);
+    // an unlinked file lives on until its last descriptor is closed
+    let fd_read = open("fileb\0", OpenFlags::RDONLY) as usize;
+    assert_eq!(unlink("fileb\0"), 0);
+    assert_eq!(open("fileb\0", OpenFlags::RDONLY), -1);
+    let read_len = read(fd_read, &mut buffer) as usize;
+    assert_eq!(test_str, core::str::from_utf8(&buffer[..read_len]).unwrap());
+    assert_eq!(fstat(fd_read, &mut stat_b), 0);
+    assert_eq!(stat_b.nlink, 0);
+    close(fd_read);
+    close(fd);
+    assert_eq!(fstat(0, &mut stat), -1);
+
+    // absolute paths refer to the read-only initramfs
+    let motd = "/etc/motd\0";
+    assert_eq!(open(motd, OpenFlags::WRONLY), -1);
+    assert_eq!(open("/etc/nothing\0", OpenFlags::RDONLY), -1);
+    let fd = open(motd, OpenFlags::RDONLY) as usize;
+    let read_len = read(fd, &mut buffer) as usize;
+    assert!(core::str::from_utf8(&buffer[..read_len])
+        .unwrap()
+        .starts_with("Welcome"));
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert_eq!(stat.dev, 1);
+    close(fd);
+    println!("Test file OK!");
+    0
+}
@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{syslog, write, SYSLOG_ACTION_READ_ALL};
+
+user_lib::kernel_only!();
+
+/// As large as the kernel log, which does not fit in the user stack
+static mut LOG_BUF: [u8; 1 << 14] = [0; 1 << 14];
+
+#[no_mangle]
+fn main() -> i32 {
+    let buf = unsafe { &mut *core::ptr::addr_of_mut!(LOG_BUF) };
+    let len = buf.len();
+    let read = syslog(SYSLOG_ACTION_READ_ALL, buf, len);
+    if read < 0 {
+        return -1;
+    }
+    write(1, &buf[..read as usize]);
+    0
+}
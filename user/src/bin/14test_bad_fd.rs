@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, dup, fstat, open, read, write, OpenFlags, Stat};
+
+user_lib::kernel_only!();
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut buf = [0u8; 4];
+    // no such fd
+    assert_eq!(write(99, b"x"), -1);
+    assert_eq!(read(99, &mut buf), -1);
+    assert_eq!(close(99), -1);
+    assert_eq!(dup(99), -1);
+    assert_eq!(fstat(99, &mut Stat::new()), -1);
+    // wrong direction
+    assert_eq!(write(0, b"x"), -1);
+    assert_eq!(read(1, &mut buf), -1);
+    // no such file, and the initramfs is read only
+    assert_eq!(open("no_such_file\0", OpenFlags::RDONLY), -1);
+    assert_eq!(open("/etc/motd\0", OpenFlags::WRONLY), -1);
+    // a closed fd is gone, and is reused by the next open
+    let fd = dup(1);
+    assert!(fd > 2);
+    assert_eq!(close(fd as usize), 0);
+    assert_eq!(write(fd as usize, b"x"), -1);
+    assert_eq!(close(fd as usize), -1);
+    assert_eq!(dup(1), fd);
+    assert_eq!(close(fd as usize), 0);
+    println!("Test bad_fd OK!");
+    0
+}
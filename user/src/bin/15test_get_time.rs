@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{get_time, yield_};
+
+#[no_mangle]
+fn main() -> i32 {
+    let start = get_time();
+    assert!(start >= 0);
+    let mut last = start;
+    // time never goes back, and goes forward while we yield
+    while last < start + 100 {
+        yield_();
+        let now = get_time();
+        assert!(now >= last);
+        last = now;
+    }
+    println!("Test get_time OK!");
+    0
+}
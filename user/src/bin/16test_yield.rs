@@ -0,0 +1,46 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{exit, thread_create, waittid, yield_};
+
+user_lib::kernel_only!();
+
+const THREADS: usize = 3;
+const ROUNDS: usize = 50;
+/// How far a thread may get ahead of another, allowing for a timer tick
+/// landing between an increment and the yield
+const MAX_LEAD: usize = 3;
+
+static ROUNDS_DONE: [AtomicUsize; THREADS] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+/// Yield `ROUNDS` times, checking that the others keep up
+fn yielder(id: usize) -> ! {
+    for round in 1..=ROUNDS {
+        ROUNDS_DONE[id].store(round, Ordering::SeqCst);
+        for other in ROUNDS_DONE.iter() {
+            // round robin runs every other ready thread between two of ours
+            assert!(round <= other.load(Ordering::SeqCst) + MAX_LEAD);
+        }
+        yield_();
+    }
+    exit(0);
+    unreachable!()
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let tids: [isize; THREADS] = core::array::from_fn(|id| thread_create(yielder as usize, id));
+    for tid in tids {
+        assert_eq!(waittid(tid as usize), Some(0));
+    }
+    println!("Test yield OK!");
+    0
+}
@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, gettid, thread_create, waittid};
+
+user_lib::kernel_only!();
+
+fn exit_with(code: usize) -> ! {
+    exit(code as i32);
+    unreachable!()
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    // -1 and -2 are exit codes like any other, not waittid errors
+    let codes = [0, 42, -7isize as usize, -1isize as usize, -2isize as usize];
+    let tids = codes.map(|code| thread_create(exit_with as usize, code));
+    for (tid, code) in tids.iter().zip(codes) {
+        assert_eq!(waittid(*tid as usize), Some(code as i32));
+        // a thread is waited for only once
+        assert_eq!(waittid(*tid as usize), None);
+    }
+    // no such thread, and no waiting for ourselves
+    assert_eq!(waittid(99), None);
+    assert_eq!(waittid(gettid() as usize), None);
+    println!("Test exit_code OK!");
+    0
+}
@@ -0,0 +1,18 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+user_lib::kernel_only!();
+
+/// Expected to be killed for a store fault
+#[no_mangle]
+fn main() -> i32 {
+    println!("Storing to address 0, the kernel should kill this app");
+    unsafe {
+        core::ptr::null_mut::<u8>().write_volatile(0);
+    }
+    println!("Test fault_store FAILED: still alive");
+    0
+}
@@ -0,0 +1,20 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+user_lib::kernel_only!();
+
+/// Expected to be killed for an illegal instruction
+#[no_mangle]
+fn main() -> i32 {
+    println!("Executing sret in U-mode, the kernel should kill this app");
+    // still built for the host, where it never runs
+    #[cfg(target_arch = "riscv64")]
+    unsafe {
+        core::arch::asm!("sret");
+    }
+    println!("Test fault_illegal FAILED: still alive");
+    0
+}
@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    condvar_create, condvar_signal, condvar_wait, enable_deadlock_detect, mutex_blocking_create,
+    mutex_create, mutex_lock, mutex_unlock, semaphore_down, semaphore_up, set_log_filter, syslog,
+    EDEADLK, SYSLOG_ACTION_SIZE_BUFFER,
+};
+
+user_lib::kernel_only!();
+
+#[no_mangle]
+fn main() -> i32 {
+    // no such mutex, semaphore or condition variable
+    assert_eq!(mutex_lock(99), -1);
+    assert_eq!(mutex_unlock(99), -1);
+    assert_eq!(semaphore_up(99), -1);
+    assert_eq!(semaphore_down(99), -1);
+    assert_eq!(condvar_signal(99), -1);
+    assert_eq!(condvar_wait(99, 99), -1);
+    // mutexes not held, or held already
+    let condvar = condvar_create() as usize;
+    for mutex in [mutex_create() as usize, mutex_blocking_create() as usize] {
+        assert_eq!(mutex_unlock(mutex), -1);
+        assert_eq!(condvar_wait(condvar, mutex), -1);
+        assert_eq!(mutex_lock(mutex), 0);
+        assert_eq!(mutex_lock(mutex), EDEADLK);
+        assert_eq!(mutex_unlock(mutex), 0);
+        assert_eq!(mutex_unlock(mutex), -1);
+    }
+    // bad arguments
+    assert_eq!(set_log_filter("task=loud\0"), -1);
+    assert_eq!(syslog(0, &mut [], 0), -1);
+    assert!(syslog(SYSLOG_ACTION_SIZE_BUFFER, &mut [], 0) > 0);
+    assert_eq!(enable_deadlock_detect(false), 0);
+    println!("Test syscall_errors OK!");
+    0
+}
@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, ProtFlags, PAGE_SIZE};
+
+user_lib::kernel_only!();
+
+#[no_mangle]
+fn main() -> i32 {
+    let rwx = ProtFlags::all();
+    // let the kernel find room, then leave a hole after it
+    let start = mmap(0, 3 * PAGE_SIZE, rwx);
+    assert!(start > 0 && start as usize % PAGE_SIZE == 0);
+    let start = start as usize;
+    assert_eq!(munmap(start + 2 * PAGE_SIZE, PAGE_SIZE), 0);
+    let pages = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, 2 * PAGE_SIZE) };
+    assert!(pages.iter().all(|byte| *byte == 0));
+    for (i, byte) in pages.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    assert!(pages.iter().enumerate().all(|(i, byte)| *byte == i as u8));
+    // overlapping, unaligned, outside the region, or too large
+    assert_eq!(mmap(start + PAGE_SIZE, PAGE_SIZE, rwx), -1);
+    assert_eq!(mmap(start + 2 * PAGE_SIZE + 8, PAGE_SIZE, rwx), -1);
+    assert_eq!(mmap(PAGE_SIZE, PAGE_SIZE, rwx), -1);
+    assert_eq!(mmap(0, usize::MAX / 2, rwx), -1);
+    // no permission at all, while any other set of them will do
+    let hole = start + 2 * PAGE_SIZE;
+    assert_eq!(mmap(hole, PAGE_SIZE, ProtFlags::empty()), -1);
+    assert_eq!(mmap(hole, PAGE_SIZE, ProtFlags::READ), hole as isize);
+    assert_eq!(munmap(hole, PAGE_SIZE), 0);
+    assert_eq!(
+        mmap(hole, PAGE_SIZE, ProtFlags::READ | ProtFlags::WRITE),
+        hole as isize
+    );
+    assert_eq!(munmap(hole, PAGE_SIZE), 0);
+    // pages not all mapped
+    assert_eq!(munmap(start, 3 * PAGE_SIZE), -1);
+    assert_eq!(munmap(start + PAGE_SIZE, PAGE_SIZE), 0);
+    assert_eq!(munmap(start + PAGE_SIZE, PAGE_SIZE), -1);
+    assert_eq!(
+        mmap(start + PAGE_SIZE, 2 * PAGE_SIZE, rwx),
+        (start + PAGE_SIZE) as isize
+    );
+    assert_eq!(munmap(start, 3 * PAGE_SIZE), 0);
+    println!("Test mmap OK!");
+    0
+}
@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut v: Vec<u32> = (0..10000).collect();
+    assert_eq!(v.iter().map(|x| *x as u64).sum::<u64>(), 49995000);
+    v.retain(|x| x % 2 == 0);
+    v.shrink_to_fit();
+    assert_eq!(v.len(), 5000);
+    let mut s = String::new();
+    for i in 0..100 {
+        write!(s, "{},", i).unwrap();
+    }
+    assert!(s.starts_with("0,1,2,") && s.ends_with("98,99,"));
+    let boxed = Box::new([7u8; 1000]);
+    assert!(boxed.iter().all(|x| *x == 7));
+    let mut map = BTreeMap::new();
+    for i in 0..1000 {
+        map.insert(i * 7 % 1000, i);
+    }
+    assert_eq!(map.len(), 1000);
+    drop(v);
+    drop(map);
+    // the freed memory is reused
+    let again: Vec<u64> = Vec::with_capacity(2000);
+    assert_eq!(again.capacity(), 2000);
+    println!("Test heap OK!");
+    0
+}
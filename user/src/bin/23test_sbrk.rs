@@ -0,0 +1,30 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::sbrk;
+
+user_lib::kernel_only!();
+
+#[no_mangle]
+fn main() -> i32 {
+    let bottom = sbrk(0);
+    assert!(bottom > 0);
+    // no memory below the end of .bss, nor beyond the app slot
+    assert_eq!(sbrk(-1), -1);
+    assert_eq!(sbrk(0x100000), -1);
+    assert_eq!(sbrk(0x1000), bottom);
+    let heap = unsafe { core::slice::from_raw_parts_mut(bottom as *mut u8, 0x1000) };
+    assert!(heap.iter().all(|byte| *byte == 0));
+    heap.fill(0xff);
+    assert_eq!(sbrk(-0x1000), bottom + 0x1000);
+    // memory given back is zeroed when taken again
+    assert_eq!(sbrk(0x1000), bottom);
+    let heap = unsafe { core::slice::from_raw_parts(bottom as *const u8, 0x1000) };
+    assert!(heap.iter().all(|byte| *byte == 0));
+    assert_eq!(sbrk(0), bottom + 0x1000);
+    println!("Test sbrk OK!");
+    0
+}
@@ -0,0 +1,16 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+user_lib::kernel_only!();
+
+/// Expected to be killed for a load fault
+#[no_mangle]
+fn main() -> i32 {
+    println!("Loading from address 0, the kernel should kill this app");
+    let byte = unsafe { core::ptr::null::<u8>().read_volatile() };
+    println!("Test fault_load FAILED: still alive, read {}", byte);
+    0
+}
@@ -0,0 +1,89 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::hint::black_box;
+use user_lib::{exit, thread_create, waittid};
+
+user_lib::kernel_only!();
+
+const ITER: usize = 2_000_000;
+
+/// Bits of f0~f31 and fcsr ORed together, all zero until the thread writes
+/// any of them
+#[cfg(target_arch = "riscv64")]
+#[inline(never)]
+fn fp_state() -> usize {
+    let mut acc: usize = 0;
+    unsafe {
+        core::arch::asm!(
+            ".altmacro",
+            ".macro OR_FP n",
+            "    fmv.x.d {tmp}, f\\n",
+            "    or {acc}, {acc}, {tmp}",
+            ".endm",
+            ".set fpn, 0",
+            ".rept 32",
+            "    OR_FP %fpn",
+            "    .set fpn, fpn+1",
+            ".endr",
+            ".purgem OR_FP",
+            ".noaltmacro",
+            "frcsr {tmp}",
+            "or {acc}, {acc}, {tmp}",
+            acc = inout(reg) acc,
+            tmp = out(reg) _,
+        );
+    }
+    acc
+}
+
+/// Nothing zeroes them on a host, where only the sums are checked
+#[cfg(not(target_arch = "riscv64"))]
+fn fp_state() -> usize {
+    0
+}
+
+/// Sum of `i * step` for `i` in `0..ITER`, long enough to be preempted many
+/// times, which is exact as every partial sum is a small multiple of `step`
+fn fp_sum(step: f64) -> f64 {
+    let step = black_box(step);
+    let mut acc = 0.0f64;
+    for i in 0..ITER {
+        acc += (i % 5) as f64 * step;
+    }
+    acc
+}
+
+/// Sum of `i % 5` for `i` in `0..ITER`
+fn int_sum() -> usize {
+    (0..ITER).map(|i| i % 5).sum()
+}
+
+fn thread_fp(_arg: usize) -> ! {
+    let state = fp_state();
+    let sum = fp_sum(0.25);
+    let ok = state == 0 && sum == int_sum() as f64 * 0.25;
+    println!("fp_init thread: state {:#x}, sum {}", state, sum);
+    exit(if ok { 0 } else { 1 });
+    unreachable!()
+}
+
+/// Every thread starts with zeroed FP registers, and keeps its own ones
+/// while it is preempted by other threads and apps using theirs
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(fp_state(), 0);
+    // leave non-zero FP registers behind for the new thread not to see
+    let expected = int_sum() as f64 * 0.5;
+    let tid = thread_create(thread_fp as usize, 0);
+    assert!(tid > 0);
+    let sum = fp_sum(0.5);
+    println!("fp_init main: sum {} (expected {})", sum, expected);
+    assert_eq!(sum, expected);
+    assert_eq!(waittid(tid as usize), Some(0));
+    println!("Test fp_init OK!");
+    0
+}
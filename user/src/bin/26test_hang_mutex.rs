@@ -0,0 +1,28 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::sync::Mutex;
+use user_lib::{exit, mutex_lock, thread_create, waittid};
+
+user_lib::kernel_only!();
+
+fn lock_and_exit(mutex_id: usize) -> ! {
+    assert_eq!(mutex_lock(mutex_id), 0);
+    exit(0);
+    unreachable!()
+}
+
+/// Expected to be killed for a deadlock
+#[no_mangle]
+fn main() -> i32 {
+    let mutex = Mutex::new_blocking();
+    let tid = thread_create(lock_and_exit as usize, mutex.id());
+    waittid(tid as usize);
+    println!("Locking a mutex left locked by an exited thread, the kernel should kill this app");
+    let _guard = mutex.lock();
+    println!("Test hang_mutex FAILED: still alive");
+    0
+}
@@ -0,0 +1,21 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{pipe, read};
+
+user_lib::kernel_only!();
+
+/// Expected to be killed for a deadlock
+#[no_mangle]
+fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    println!("Reading from a pipe only this app can write to, the kernel should kill this app");
+    let mut buf = [0u8; 1];
+    let len = read(pipe_fd[0], &mut buf);
+    println!("Test hang_pipe FAILED: read returned {}", len);
+    0
+}
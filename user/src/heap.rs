@@ -0,0 +1,49 @@
+//! The heap of `alloc` collections, grown with `sbrk`
+//!
+//! The heap starts empty and grows by at least [`HEAP_GROW_SIZE`] bytes when
+//! an allocation does not fit, so it needs the program break to itself: apps
+//! using `alloc` must not move it with [`crate::sbrk`].
+
+use super::sys_sbrk;
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::{self, NonNull};
+use linked_list_allocator::LockedHeap;
+
+/// Smallest size the heap grows by
+const HEAP_GROW_SIZE: usize = 0x1000;
+
+struct UserHeap(LockedHeap);
+
+#[global_allocator]
+static HEAP: UserHeap = UserHeap(LockedHeap::empty());
+
+unsafe impl GlobalAlloc for UserHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        if let Ok(ptr) = heap.allocate_first_fit(layout) {
+            return ptr.as_ptr();
+        }
+        let size = (layout.size() + layout.align()).max(HEAP_GROW_SIZE);
+        let old_brk = sys_sbrk(size as isize);
+        if old_brk < 0 {
+            return ptr::null_mut();
+        }
+        if heap.size() == 0 {
+            heap.init(old_brk as *mut u8, size);
+        } else if old_brk as usize == heap.top() as usize {
+            heap.extend(size);
+        } else {
+            // the break was moved behind our back
+            sys_sbrk(-(size as isize));
+            return ptr::null_mut();
+        }
+        heap.allocate_first_fit(layout)
+            .map_or(ptr::null_mut(), NonNull::as_ptr)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0
+            .lock()
+            .deallocate(NonNull::new_unchecked(ptr), layout);
+    }
+}
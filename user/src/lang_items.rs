@@ -1,3 +1,5 @@
+use crate::{exit, PANIC_EXIT_CODE};
+
 #[panic_handler]
 fn panic_handler(panic_info: &core::panic::PanicInfo) -> ! {
     let err = panic_info.message().unwrap();
@@ -11,5 +13,6 @@ fn panic_handler(panic_info: &core::panic::PanicInfo) -> ! {
     } else {
         println!("Panicked: {}", err);
     }
-    loop {}
+    exit(PANIC_EXIT_CODE);
+    unreachable!()
 }
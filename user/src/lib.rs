@@ -4,23 +4,107 @@
 
 #[macro_use]
 pub mod console;
+#[cfg(target_os = "none")]
+mod heap;
+#[cfg(target_os = "none")]
 mod lang_items;
+pub mod sync;
 mod syscall;
+#[cfg(target_os = "none")]
+mod tls;
 
+#[cfg(target_os = "none")]
 #[no_mangle]
 #[link_section = ".text.entry"]
 pub extern "C" fn _start() -> ! {
     clear_bss();
+    tls::check();
+    tls::enter(tls::alloc_block(0, 0).unwrap());
     exit(main());
     panic!("unreachable after sys_exit!");
 }
 
+#[cfg(target_os = "none")]
 #[linkage = "weak"]
 #[no_mangle]
 fn main() -> i32 {
     panic!("Cannot find main!");
 }
 
+bitflags! {
+    /// Flags of [`open`], which opens for reading only unless `WRONLY` or
+    /// `RDWR` is given
+    pub struct OpenFlags: u32 {
+        const WRONLY = 1 << 0;
+        const RDWR = 1 << 1;
+        const CREATE = 1 << 9;
+        const TRUNC = 1 << 10;
+    }
+}
+
+impl OpenFlags {
+    pub const RDONLY: Self = Self::empty();
+}
+
+/// Status of a file, filled in by [`fstat`]
+#[repr(C)]
+#[derive(Debug)]
+pub struct Stat {
+    /// ID of device containing file
+    pub dev: u64,
+    /// inode number
+    pub ino: u64,
+    /// file type and mode
+    pub mode: StatMode,
+    /// number of hard links
+    pub nlink: u32,
+    /// unused pad
+    pad: [u64; 7],
+}
+
+impl Stat {
+    pub fn new() -> Self {
+        Stat {
+            dev: 0,
+            ino: 0,
+            mode: StatMode::empty(),
+            nlink: 0,
+            pad: [0; 7],
+        }
+    }
+}
+
+impl Default for Stat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+bitflags! {
+    pub struct StatMode: u32 {
+        /// directory
+        const DIR   = 0o040000;
+        /// ordinary regular file
+        const FILE  = 0o100000;
+    }
+}
+
+bitflags! {
+    /// Permissions of memory mapped by [`mmap`]. The kernel cannot enforce
+    /// them: the memory stays readable, writable and executable.
+    pub struct ProtFlags: usize {
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+        const EXEC = 1 << 2;
+    }
+}
+
+pub const PAGE_SIZE: usize = 0x1000;
+
+/// Directory fd meaning paths are not relative to an open directory
+pub const AT_FDCWD: usize = -100isize as usize;
+
+#[cfg(target_os = "none")]
 fn clear_bss() {
     extern "C" {
         fn start_bss();
@@ -31,17 +115,191 @@ fn clear_bss() {
     });
 }
 
+use bitflags::bitflags;
 use syscall::*;
 
+/// Actions of [`syslog`]: read the log into `buf`
+pub const SYSLOG_ACTION_READ_ALL: usize = 3;
+/// Read the log into `buf`, then clear it
+pub const SYSLOG_ACTION_READ_CLEAR: usize = 4;
+/// Clear the log
+pub const SYSLOG_ACTION_CLEAR: usize = 5;
+/// Echo records up to level `len` to the console, 0 for none and 1 (error)
+/// to 5 (trace)
+pub const SYSLOG_ACTION_CONSOLE_LEVEL: usize = 8;
+/// Return the number of bytes in the log
+pub const SYSLOG_ACTION_SIZE_UNREAD: usize = 9;
+/// Return the size of the log buffer
+pub const SYSLOG_ACTION_SIZE_BUFFER: usize = 10;
+
+/// Exit code of a thread that panicked, as with Rust std
+pub const PANIC_EXIT_CODE: i32 = 101;
+
+/// Mark an app as needing the kernel, for files, threads, synchronization,
+/// memory or faults. Built for the host, such an app exits with 0 without
+/// running its `main`, so `cargo test` on the host passes over it.
+#[macro_export]
+macro_rules! kernel_only {
+    () => {
+        #[no_mangle]
+        static KERNEL_ONLY: bool = true;
+    };
+}
+
+/// Returned by lock and down requests refused by deadlock detection, and by
+/// locks of a mutex by the thread holding it
+pub const EDEADLK: isize = -0xDEAD;
+
+/// Open the file `path`, which must end with `\0`, and return its fd
+pub fn open(path: &str, flags: OpenFlags) -> isize {
+    sys_open(path, flags.bits)
+}
+pub fn fstat(fd: usize, st: &mut Stat) -> isize {
+    sys_fstat(fd, st)
+}
+/// Create `new_path` as a hard link to `old_path`, both ending with `\0`
+pub fn link(old_path: &str, new_path: &str) -> isize {
+    sys_linkat(AT_FDCWD, old_path, AT_FDCWD, new_path, 0)
+}
+/// Remove the link `path`, which must end with `\0`
+pub fn unlink(path: &str) -> isize {
+    sys_unlinkat(AT_FDCWD, path, 0)
+}
+pub fn dup(fd: usize) -> isize {
+    sys_dup(fd)
+}
+pub fn close(fd: usize) -> isize {
+    sys_close(fd)
+}
+/// Create a pipe, storing the fd of its read end in `pipe_fd[0]` and the fd
+/// of its write end in `pipe_fd[1]`
+pub fn pipe(pipe_fd: &mut [usize]) -> isize {
+    assert!(pipe_fd.len() >= 2);
+    sys_pipe(pipe_fd)
+}
+pub fn read(fd: usize, buf: &mut [u8]) -> isize {
+    sys_read(fd, buf)
+}
 pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
 pub fn exit(exit_code: i32) -> isize {
+    #[cfg(target_os = "none")]
+    tls::release();
     sys_exit(exit_code)
 }
+/// Operate on the kernel log with one of the `SYSLOG_ACTION_*` actions.
+/// Reads copy up to `buf.len()` bytes of whole records, while
+/// `SYSLOG_ACTION_CONSOLE_LEVEL` takes its level in `len`.
+pub fn syslog(log_type: usize, buf: &mut [u8], len: usize) -> isize {
+    sys_syslog(log_type, buf, len)
+}
+/// Replace the kernel log filter with `spec`, which must end with `\0`,
+/// like `info,task=trace,trap=warn\0`
+pub fn set_log_filter(spec: &str) -> isize {
+    sys_set_log_filter(spec)
+}
 pub fn yield_() -> isize {
     sys_yield()
 }
 pub fn get_time() -> isize {
     sys_get_time()
 }
+
+/// Move the program break by `increment` bytes, which may be negative, and
+/// return the old break, or -1 if it would leave the app slot or go below
+/// the end of `.bss`. The heap of `alloc` collections grows the break, so
+/// apps using them should not call this.
+pub fn sbrk(increment: isize) -> isize {
+    sys_sbrk(increment)
+}
+/// Map `len` bytes of zeroed memory at `start`, a page-aligned address
+/// within the mmap region of the kernel, or where the kernel finds room if
+/// `start` is 0, and return its address. Fail if `prot` is empty or any of
+/// the pages is mapped already.
+///
+/// The region is shared by all apps, so other apps can reach the memory too.
+pub fn mmap(start: usize, len: usize, prot: ProtFlags) -> isize {
+    sys_mmap(start, len, prot.bits)
+}
+/// Unmap `len` bytes at `start`, which must all be mapped by this app
+pub fn munmap(start: usize, len: usize) -> isize {
+    sys_munmap(start, len)
+}
+
+pub fn mutex_create() -> isize {
+    sys_mutex_create(false)
+}
+pub fn mutex_blocking_create() -> isize {
+    sys_mutex_create(true)
+}
+pub fn mutex_lock(mutex_id: usize) -> isize {
+    sys_mutex_lock(mutex_id)
+}
+pub fn mutex_unlock(mutex_id: usize) -> isize {
+    sys_mutex_unlock(mutex_id)
+}
+pub fn semaphore_create(res_count: usize) -> isize {
+    sys_semaphore_create(res_count)
+}
+pub fn semaphore_up(sem_id: usize) -> isize {
+    sys_semaphore_up(sem_id)
+}
+pub fn semaphore_down(sem_id: usize) -> isize {
+    sys_semaphore_down(sem_id)
+}
+pub fn condvar_create() -> isize {
+    sys_condvar_create()
+}
+pub fn condvar_signal(condvar_id: usize) -> isize {
+    sys_condvar_signal(condvar_id)
+}
+pub fn condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    sys_condvar_wait(condvar_id, mutex_id)
+}
+pub fn enable_deadlock_detect(enabled: bool) -> isize {
+    sys_enable_deadlock_detect(enabled as usize)
+}
+/// Create a thread running `entry(arg)`, which must end with [`exit`], and
+/// return its tid, or -1 if there is no thread-local storage left for it
+#[cfg(target_os = "none")]
+pub fn thread_create(entry: usize, arg: usize) -> isize {
+    let block = match tls::alloc_block(entry, arg) {
+        Some(block) => block,
+        None => return -1,
+    };
+    let tid = sys_thread_create(thread_start as usize, block);
+    if tid < 0 {
+        tls::free_block(block);
+    }
+    tid
+}
+/// Threads are not supported on the host, where this returns -1
+#[cfg(not(target_os = "none"))]
+pub fn thread_create(entry: usize, arg: usize) -> isize {
+    sys_thread_create(entry, arg)
+}
+/// Every thread starts here to set up its thread-local storage first
+#[cfg(target_os = "none")]
+extern "C" fn thread_start(tls_block: usize) -> ! {
+    let (entry, arg) = tls::enter(tls_block);
+    let entry: fn(usize) -> ! = unsafe { core::mem::transmute(entry) };
+    entry(arg)
+}
+pub fn gettid() -> isize {
+    sys_gettid()
+}
+/// Wait for thread `tid` to exit and return its exit code, or `None` if
+/// there is no such thread
+pub fn waittid(tid: usize) -> Option<i32> {
+    let mut exit_code = 0;
+    loop {
+        match sys_waittid(tid, &mut exit_code) {
+            0 => return Some(exit_code),
+            -2 => {
+                yield_();
+            }
+            _ => return None,
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! RAII-style wrappers around the kernel synchronization syscalls
+//!
+//! Kernel objects belong to the app creating them and live until it exits,
+//! so dropping a wrapper never destroys the object behind it. Threads of the
+//! app share an object by passing its id to `from_id`.
+
+use super::{
+    condvar_create, condvar_signal, condvar_wait, mutex_blocking_create, mutex_create, mutex_lock,
+    mutex_unlock, semaphore_create, semaphore_down, semaphore_up,
+};
+
+/// A kernel mutex, locked through [`Mutex::lock`]
+pub struct Mutex {
+    id: usize,
+}
+
+/// The lock of a [`Mutex`] is held until this guard is dropped
+pub struct MutexGuard<'a> {
+    mutex: &'a Mutex,
+}
+
+impl Mutex {
+    /// Create a mutex on which waiting tasks keep yielding
+    pub fn new() -> Self {
+        Self::from_id(mutex_create() as usize)
+    }
+    /// Create a mutex on which waiting tasks are blocked by the kernel
+    pub fn new_blocking() -> Self {
+        Self::from_id(mutex_blocking_create() as usize)
+    }
+    /// Refer to an existing kernel mutex
+    pub fn from_id(id: usize) -> Self {
+        Self { id }
+    }
+    /// Id of the kernel mutex
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    /// Lock the mutex, blocking until it is available
+    pub fn lock(&self) -> MutexGuard<'_> {
+        self.lock_checked().unwrap()
+    }
+    /// Lock the mutex, or return the error code if the kernel refused to,
+    /// e.g. [`EDEADLK`](crate::EDEADLK) when deadlock detection is on or
+    /// this thread holds it already
+    pub fn lock_checked(&self) -> Result<MutexGuard<'_>, isize> {
+        match mutex_lock(self.id) {
+            0 => Ok(MutexGuard { mutex: self }),
+            err => Err(err),
+        }
+    }
+}
+
+impl Default for Mutex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for MutexGuard<'_> {
+    fn drop(&mut self) {
+        mutex_unlock(self.mutex.id);
+    }
+}
+
+/// A kernel counting semaphore
+pub struct Semaphore {
+    id: usize,
+}
+
+impl Semaphore {
+    /// Create a semaphore holding `res_count` resources
+    pub fn new(res_count: usize) -> Self {
+        Self::from_id(semaphore_create(res_count) as usize)
+    }
+    /// Refer to an existing kernel semaphore
+    pub fn from_id(id: usize) -> Self {
+        Self { id }
+    }
+    /// Id of the kernel semaphore
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    /// Release a resource, waking up a waiting task if there is one
+    pub fn up(&self) {
+        assert_eq!(semaphore_up(self.id), 0);
+    }
+    /// Acquire a resource, blocking until one is available
+    pub fn down(&self) {
+        self.down_checked().unwrap();
+    }
+    /// Acquire a resource, or return the error code if the kernel refused
+    /// to, e.g. [`EDEADLK`](crate::EDEADLK) when deadlock detection is on
+    pub fn down_checked(&self) -> Result<(), isize> {
+        match semaphore_down(self.id) {
+            0 => Ok(()),
+            err => Err(err),
+        }
+    }
+}
+
+/// A kernel condition variable, used together with a [`Mutex`]
+pub struct Condvar {
+    id: usize,
+}
+
+impl Condvar {
+    /// Create a condition variable
+    pub fn new() -> Self {
+        Self::from_id(condvar_create() as usize)
+    }
+    /// Refer to an existing kernel condition variable
+    pub fn from_id(id: usize) -> Self {
+        Self { id }
+    }
+    /// Id of the kernel condition variable
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    /// Wake up a task waiting on the condition variable
+    pub fn signal(&self) {
+        assert_eq!(condvar_signal(self.id), 0);
+    }
+    /// Release the lock held by `guard` and wait for a signal, then take the
+    /// lock again before returning
+    pub fn wait<'a>(&self, guard: MutexGuard<'a>) -> MutexGuard<'a> {
+        self.wait_checked(guard).unwrap()
+    }
+    /// Like [`Condvar::wait`], but return the error code if the kernel
+    /// refused to take the lock again, e.g. [`EDEADLK`](crate::EDEADLK)
+    /// when deadlock detection is on, leaving the mutex unlocked
+    pub fn wait_checked<'a>(&self, guard: MutexGuard<'a>) -> Result<MutexGuard<'a>, isize> {
+        match condvar_wait(self.id, guard.mutex.id) {
+            0 => Ok(guard),
+            err => {
+                // the lock is not held any more
+                core::mem::forget(guard);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
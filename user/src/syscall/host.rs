@@ -0,0 +1,120 @@
+//! Syscalls of apps built for the host, mapped onto std
+//!
+//! With a host target like `x86_64-unknown-linux-gnu`, `user_lib` links std
+//! and apps run as ordinary processes. The `main` of an app is a Rust
+//! function, so libc calls the C `main` of [`__wrap_main`] instead, which
+//! calls it in turn: `.cargo/config.toml` links apps built for the host with
+//! `--wrap=main`, which needs a linker supporting it, like GNU ld or lld on
+//! ELF hosts. Syscalls are mapped as follows:
+//!
+//! - `write` to fd 1 and 2 goes to stdout and stderr, and `read` from fd 0
+//!   comes from stdin
+//! - `exit` ends the process with `std::process::exit`
+//! - `yield` is `std::thread::yield_now`
+//! - `get_time` is the time since the process started, in milliseconds
+//!
+//! Every other syscall, files, threads and synchronization included, returns
+//! -1 with a note on stderr. Apps needing them are marked with
+//! [`kernel_only!`](crate::kernel_only), and exit with 0 right away, so that
+//! `cargo test` runs every app and only these are passed over. A panic prints
+//! its message and exits with [`PANIC_EXIT_CODE`], as it does under the
+//! kernel.
+
+extern crate std;
+
+use super::{SYSCALL_EXIT, SYSCALL_GET_TIME, SYSCALL_READ, SYSCALL_WRITE, SYSCALL_YIELD};
+use crate::PANIC_EXIT_CODE;
+use core::ffi::{c_char, c_int};
+use std::boxed::Box;
+use std::io::{self, Read, Write};
+use std::string::String;
+use std::sync::OnceLock;
+use std::time::Instant;
+use std::{eprintln, process, slice, thread};
+
+/// When the process started, for `get_time`
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// Whether the app needs the kernel, replaced by the one `kernel_only!`
+/// defines in such an app
+#[linkage = "weak"]
+#[no_mangle]
+static KERNEL_ONLY: bool = false;
+
+/// Set up what the Rust runtime would, which apps do without
+fn init() {
+    START.get_or_init(Instant::now);
+    std::panic::set_hook(Box::new(|info| {
+        let payload = info.payload();
+        let err = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+        if let Some(location) = info.location() {
+            println!(
+                "Panicked at {}:{}, {}",
+                location.file(),
+                location.line(),
+                err
+            );
+        } else {
+            println!("Panicked: {}", err);
+        }
+        process::exit(PANIC_EXIT_CODE);
+    }));
+}
+
+/// The C `main` that libc calls in place of the `main` of the app, which
+/// `--wrap=main` makes reachable as `__real_main`
+#[no_mangle]
+extern "C" fn __wrap_main(_argc: c_int, _argv: *const *const c_char) -> c_int {
+    extern "Rust" {
+        fn __real_main() -> i32;
+    }
+    // read at run time, as the app may have replaced it
+    if unsafe { core::ptr::read_volatile(&KERNEL_ONLY) } {
+        eprintln!("[host] This app needs the kernel, skipped");
+        return 0;
+    }
+    init();
+    unsafe { __real_main() }
+}
+
+pub fn syscall(id: usize, args: [usize; 6]) -> isize {
+    match id {
+        SYSCALL_WRITE => write(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_READ => read(args[0], args[1] as *mut u8, args[2]),
+        SYSCALL_EXIT => process::exit(args[0] as i32),
+        SYSCALL_YIELD => {
+            thread::yield_now();
+            0
+        }
+        SYSCALL_GET_TIME => START.get_or_init(Instant::now).elapsed().as_millis() as isize,
+        _ => {
+            eprintln!("[host] Unsupported syscall {}", id);
+            -1
+        }
+    }
+}
+
+fn write(fd: usize, buf: *const u8, len: usize) -> isize {
+    let buf = unsafe { slice::from_raw_parts(buf, len) };
+    let result = match fd {
+        1 => {
+            let mut stdout = io::stdout();
+            stdout.write_all(buf).and_then(|_| stdout.flush())
+        }
+        2 => io::stderr().write_all(buf),
+        _ => return -1,
+    };
+    result.map_or(-1, |_| len as isize)
+}
+
+fn read(fd: usize, buf: *mut u8, len: usize) -> isize {
+    let buf = unsafe { slice::from_raw_parts_mut(buf, len) };
+    match fd {
+        0 => io::stdin().read(buf).map_or(-1, |n| n as isize),
+        _ => -1,
+    }
+}
@@ -0,0 +1,107 @@
+//! Thread-local storage
+//!
+//! `#[thread_local]` statics live in the `.tdata` and `.tbss` sections,
+//! i.e. the PT_TLS segment of the app. Apps are loaded as flat binaries
+//! without program headers, so `linker.ld` marks the segment with symbols
+//! instead. Each thread gets its own copy of the segment in a block taken
+//! from a pool, and `tp` points at the block as RISC-V local-exec TLS
+//! expects.
+//!
+//! `linker.ld` reserves the pool at the end of `.bss`, with room for
+//! [`MAX_TLS_THREADS`] blocks as large as the segment rounded up to
+//! [`TLS_ALIGN`], and refuses to link an app whose thread-local statics
+//! need a larger alignment.
+
+use core::arch::asm;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Number of threads that can have thread-local storage at the same time,
+/// the main thread included, as reserved by `linker.ld`
+const MAX_TLS_THREADS: usize = 16;
+/// Alignment of each block, which `linker.ld` checks is enough for the
+/// segment
+const TLS_ALIGN: usize = 64;
+
+static TLS_USED: [AtomicBool; MAX_TLS_THREADS] =
+    [const { AtomicBool::new(false) }; MAX_TLS_THREADS];
+/// Entry and argument of the thread each block is reserved for
+static mut THREAD_START: [(usize, usize); MAX_TLS_THREADS] = [(0, 0); MAX_TLS_THREADS];
+
+extern "C" {
+    fn start_tdata();
+    fn end_tdata();
+    fn end_tbss();
+    fn start_tls_pool();
+    fn end_tls_pool();
+}
+
+/// Size of the segment, and of a block holding it
+fn sizes() -> (usize, usize) {
+    let tls_len = end_tbss as usize - start_tdata as usize;
+    let block_size = tls_len.next_multiple_of(TLS_ALIGN).max(TLS_ALIGN);
+    (tls_len, block_size)
+}
+
+/// Address of block `block` in the pool
+fn block_base(block: usize) -> usize {
+    start_tls_pool as usize + block * sizes().1
+}
+
+/// Check that the pool reserved by `linker.ld` has the blocks this module
+/// expects, before any of them is used
+pub fn check() {
+    let pool_len = end_tls_pool as usize - start_tls_pool as usize;
+    assert!(
+        start_tls_pool as usize % TLS_ALIGN == 0 && pool_len == sizes().1 * MAX_TLS_THREADS,
+        "Thread-local storage pool does not match linker.ld!"
+    );
+}
+
+/// Reserve a block for a thread that will run `entry(arg)`, and return it,
+/// or `None` if every block is taken
+pub fn alloc_block(entry: usize, arg: usize) -> Option<usize> {
+    let block = (0..MAX_TLS_THREADS).find(|&i| {
+        TLS_USED[i]
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
+    })?;
+    unsafe {
+        (*core::ptr::addr_of_mut!(THREAD_START))[block] = (entry, arg);
+    }
+    Some(block)
+}
+
+/// Give back `block`, reserved for a thread that could not be created
+pub fn free_block(block: usize) {
+    TLS_USED[block].store(false, Ordering::Release);
+}
+
+/// Copy the initial thread-local statics into `block`, point `tp` at it, and
+/// return the entry and argument the block was reserved for
+pub fn enter(block: usize) -> (usize, usize) {
+    let tdata_len = end_tdata as usize - start_tdata as usize;
+    let (tls_len, _) = sizes();
+    let base = block_base(block);
+    unsafe {
+        let data = core::slice::from_raw_parts_mut(base as *mut u8, tls_len);
+        data[..tdata_len].copy_from_slice(core::slice::from_raw_parts(
+            start_tdata as usize as *const u8,
+            tdata_len,
+        ));
+        data[tdata_len..].fill(0);
+        asm!("mv tp, {}", in(reg) base);
+        (*core::ptr::addr_of!(THREAD_START))[block]
+    }
+}
+
+/// Give the block of the current thread back, as it is about to exit
+pub fn release() {
+    let tp: usize;
+    unsafe {
+        asm!("mv {}, tp", out(reg) tp);
+    }
+    let base = start_tls_pool as usize;
+    if (base..end_tls_pool as usize).contains(&tp) {
+        free_block((tp - base) / sizes().1);
+    }
+}